@@ -1,4 +1,5 @@
-use super::{Graph, NodeId, Length};
+use super::{Graph, NodeId, Length, HalfEdge, RoadType};
+use super::{MOTORWAY, MOTORWAY_LINK, TRUNK, TRUNK_LINK, LIVING_STREET};
 
 use std::time::Instant;
 use std::cmp::Ordering;
@@ -117,15 +118,86 @@ fn count() {
             NodeInfo::new(5, 2.3, 3.4, 0),
         ],
         vec![
-            EdgeInfo::new(0, 1, 3, 3),
-            EdgeInfo::new(0, 2, 3, 3),
-            EdgeInfo::new(2, 3, 3, 3),
-            EdgeInfo::new(4, 0, 3, 3),
+            EdgeInfo::new(0, 1, 3, 1, 3),
+            EdgeInfo::new(0, 2, 3, 1, 3),
+            EdgeInfo::new(2, 3, 3, 1, 3),
+            EdgeInfo::new(4, 0, 3, 1, 3),
         ],
     );
     assert_eq!(g.count_components(), 1)
 }
 
+/// Cost factors per road type in percent of the edge length. Road types
+/// without a factor are avoided completely. Additionally every meter of ascent
+/// along an edge can be charged as `climb_factor` meters of road.
+#[derive(Clone, Debug, Default)]
+pub struct QueryOptions {
+    factors: Vec<Option<usize>>,
+    climb_factor: Length,
+}
+
+#[allow(dead_code)]
+impl QueryOptions {
+    pub fn new() -> QueryOptions {
+        Default::default()
+    }
+
+    /// Options for bicycles, which may not use motorways and trunk roads and
+    /// prefer flat routes.
+    pub fn bicycle() -> QueryOptions {
+        let mut options = QueryOptions::new();
+        for &road_type in &[MOTORWAY, MOTORWAY_LINK, TRUNK, TRUNK_LINK] {
+            options.avoid(road_type);
+        }
+        options.penalise_climb(8);
+        options
+    }
+
+    /// Options for trucks, which stay out of living streets and slow down on
+    /// steep roads.
+    pub fn truck() -> QueryOptions {
+        let mut options = QueryOptions::new();
+        options.avoid(LIVING_STREET);
+        options.penalise_climb(4);
+        options
+    }
+
+    pub fn avoid(&mut self, road_type: RoadType) -> &mut QueryOptions {
+        self.set_factor(road_type, None)
+    }
+
+    pub fn penalise(&mut self, road_type: RoadType, percent: usize) -> &mut QueryOptions {
+        self.set_factor(road_type, Some(percent))
+    }
+
+    pub fn penalise_climb(&mut self, factor: Length) -> &mut QueryOptions {
+        self.climb_factor = factor;
+        self
+    }
+
+    fn set_factor(&mut self, road_type: RoadType, factor: Option<usize>) -> &mut QueryOptions {
+        let road_type = road_type as usize;
+        if self.factors.len() <= road_type {
+            self.factors.resize(road_type + 1, Some(100));
+        }
+        self.factors[road_type] = factor;
+        self
+    }
+
+    fn cost(&self, graph: &Graph, source: NodeId, edge: &HalfEdge) -> Option<Length> {
+        let weight = match self.factors.get(edge.road_type as usize) {
+            Some(&Some(factor)) => edge.weight * factor / 100,
+            Some(&None) => return None,
+            None => edge.weight,
+        };
+        if self.climb_factor == 0 {
+            return Some(weight);
+        }
+        let (ascent, _) = graph.climb(source, edge);
+        Some(weight + ascent * self.climb_factor)
+    }
+}
+
 pub struct Dijkstra<'a> {
     dist: Vec<Length>,
     touched: Vec<NodeId>,
@@ -134,6 +206,17 @@ pub struct Dijkstra<'a> {
 
 impl<'a> Dijkstra<'a> {
     pub fn distance(&mut self, source: NodeId, dest: NodeId) -> Option<(Length, VecDeque<NodeId>)> {
+        self.distance_with(source, dest, &QueryOptions::default())
+    }
+
+    /// Like `distance`, but edge weights are scaled by the factors of `options`
+    /// and avoided road types are not used.
+    pub fn distance_with(
+        &mut self,
+        source: NodeId,
+        dest: NodeId,
+        options: &QueryOptions,
+    ) -> Option<(Length, VecDeque<NodeId>)> {
         use std::collections::BinaryHeap;
 
         for node in self.touched.drain(..) {
@@ -164,9 +247,13 @@ impl<'a> Dijkstra<'a> {
                 continue;
             }
             for edge in self.graph.outgoing_edges_for(node) {
+                let weight = match options.cost(self.graph, node, edge) {
+                    Some(weight) => weight,
+                    None => continue,
+                };
                 let next = NodeCost {
                     node: edge.endpoint,
-                    cost: cost + edge.weight,
+                    cost: cost + weight,
                 };
                 if next.cost < self.dist[next.node] {
                     prev[next.node] = node;
@@ -180,3 +267,32 @@ impl<'a> Dijkstra<'a> {
         None
     }
 }
+
+#[test]
+fn road_type_options() {
+    use super::{EdgeInfo, NodeInfo, RESIDENTIAL};
+    let g = Graph::new(
+        vec![
+            NodeInfo::new(1, 2.3, 3.4, 0),
+            NodeInfo::new(2, 2.3, 3.4, 0),
+            NodeInfo::new(3, 2.3, 3.4, 0),
+        ],
+        vec![
+            EdgeInfo::new(0, 2, 10, MOTORWAY, 130),
+            EdgeInfo::new(0, 1, 10, RESIDENTIAL, 30),
+            EdgeInfo::new(1, 2, 10, RESIDENTIAL, 30),
+        ],
+    );
+    let mut d = g.dijkstra();
+    assert_eq!(d.distance(0, 2).unwrap().0, 10);
+
+    let (dist, path) = d.distance_with(0, 2, &QueryOptions::bicycle()).unwrap();
+    assert_eq!(dist, 20);
+    assert_eq!(path, vec![0, 1, 2]);
+
+    let mut options = QueryOptions::new();
+    options.penalise(MOTORWAY, 150);
+    assert_eq!(d.distance_with(0, 2, &options).unwrap().0, 15);
+    options.penalise(MOTORWAY, 300);
+    assert_eq!(d.distance_with(0, 2, &options).unwrap().0, 20);
+}
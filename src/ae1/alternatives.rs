@@ -0,0 +1,375 @@
+use ae1::*;
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+
+/// A path through the graph with its length.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub length: Length,
+    pub path: Vec<NodeId>,
+}
+
+/// Limits for `Graph::alternative_routes`. Routes may share at most
+/// `max_overlap` of their length with previously found routes and may be at
+/// most `max_stretch` times longer than the shortest route. After each found
+/// route the weights of its edges are multiplied by `penalty`.
+#[derive(Debug, Clone)]
+pub struct AlternativeOptions {
+    pub max_routes: usize,
+    pub max_overlap: f64,
+    pub max_stretch: f64,
+    pub penalty: f64,
+}
+
+/// Number of penalised searches `Graph::alternative_routes` runs per wanted
+/// route before it gives up, since a search may find a route that is
+/// rejected or was found before.
+const SEARCHES_PER_ROUTE: usize = 3;
+
+impl Default for AlternativeOptions {
+    fn default() -> AlternativeOptions {
+        AlternativeOptions {
+            max_routes: 3,
+            max_overlap: 0.5,
+            max_stretch: 1.3,
+            penalty: 1.4,
+        }
+    }
+}
+
+/// Route with the ids of the edges it uses, ordered by length for the heap of
+/// candidates in Yen's algorithm.
+#[derive(Debug, PartialEq, Eq)]
+struct Candidate {
+    length: Length,
+    nodes: Vec<NodeId>,
+    edges: Vec<EdgeId>,
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.length.cmp(&self.length).then_with(
+            || other.nodes.cmp(&self.nodes),
+        )
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Candidate {
+    fn into_route(self) -> Route {
+        Route {
+            length: self.length,
+            path: self.nodes,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+struct NodeCost {
+    node: NodeId,
+    cost: usize,
+}
+
+impl Ord for NodeCost {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.cmp(&self.cost)
+    }
+}
+
+impl PartialOrd for NodeCost {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Distances and predecessors of `restricted_dijkstra`, kept between
+/// searches. Only the entries of `touched` nodes are reset, so a search
+/// costs nothing for the parts of the graph it doesn't reach.
+struct SearchState {
+    dist: Vec<Length>,
+    prev_edge: Vec<EdgeId>,
+    touched: Vec<NodeId>,
+    heap: BinaryHeap<NodeCost>,
+}
+
+impl SearchState {
+    fn new(node_count: usize) -> SearchState {
+        SearchState {
+            dist: vec![usize::MAX; node_count],
+            prev_edge: vec![usize::MAX; node_count],
+            touched: Vec::new(),
+            heap: BinaryHeap::new(),
+        }
+    }
+
+    fn reset(&mut self) {
+        for &node in &self.touched {
+            self.dist[node] = usize::MAX;
+            self.prev_edge[node] = usize::MAX;
+        }
+        self.touched.clear();
+        self.heap.clear();
+    }
+}
+
+impl Graph {
+    /// The `k` shortest loopless paths from `source` to `dest` in ascending
+    /// order of length, computed with Yen's algorithm.
+    pub fn k_shortest_paths(&self, source: NodeId, dest: NodeId, k: usize) -> Vec<Route> {
+        if k == 0 {
+            return Vec::new();
+        }
+        let weights = self.edge_weights();
+        let mut blocked_nodes = vec![false; self.node_count()];
+        let mut blocked_edges = HashSet::new();
+        let mut state = SearchState::new(self.node_count());
+
+        let mut found: Vec<Candidate> = Vec::new();
+        let blocked = (&blocked_nodes[..], &blocked_edges);
+        match self.restricted_dijkstra(&mut state, &weights, source, dest, blocked) {
+            Some(shortest) => found.push(shortest),
+            None => return Vec::new(),
+        }
+        let mut candidates = BinaryHeap::new();
+        let mut known: HashSet<Vec<NodeId>> = HashSet::new();
+        known.insert(found[0].nodes.clone());
+
+        while found.len() < k {
+            {
+                let last = &found[found.len() - 1];
+                for i in 0..last.nodes.len() - 1 {
+                    let spur = last.nodes[i];
+                    let root = &last.nodes[..i + 1];
+                    for route in found.iter().filter(|r| r.nodes.starts_with(root)) {
+                        blocked_edges.insert(route.edges[i]);
+                    }
+                    for &node in &root[..i] {
+                        blocked_nodes[node] = true;
+                    }
+
+                    let blocked = (&blocked_nodes[..], &blocked_edges);
+                    if let Some(spur_path) =
+                        self.restricted_dijkstra(&mut state, &weights, spur, dest, blocked)
+                    {
+                        let mut nodes = root[..i].to_vec();
+                        nodes.extend(spur_path.nodes);
+                        if known.insert(nodes.clone()) {
+                            let mut edges = last.edges[..i].to_vec();
+                            edges.extend(spur_path.edges);
+                            let root_length: Length = last.edges[..i].iter().map(|&e| weights[e]).sum();
+                            candidates.push(Candidate {
+                                length: root_length + spur_path.length,
+                                nodes,
+                                edges,
+                            });
+                        }
+                    }
+
+                    blocked_edges.clear();
+                    for &node in &root[..i] {
+                        blocked_nodes[node] = false;
+                    }
+                }
+            }
+            match candidates.pop() {
+                Some(next) => found.push(next),
+                None => break,
+            }
+        }
+
+        found.into_iter().map(Candidate::into_route).collect()
+    }
+
+    /// Up to `options.max_routes` routes from `source` to `dest` found with the
+    /// penalty method, starting with the shortest one.
+    pub fn alternative_routes(
+        &self,
+        source: NodeId,
+        dest: NodeId,
+        options: &AlternativeOptions,
+    ) -> Vec<Route> {
+        let original = self.edge_weights();
+        let mut weights = original.clone();
+        let blocked_nodes = vec![false; self.node_count()];
+        let blocked_edges = HashSet::new();
+        let mut state = SearchState::new(self.node_count());
+
+        let mut routes: Vec<Candidate> = Vec::new();
+        let mut used = HashSet::new();
+        let mut optimum = None;
+        for _ in 0..options.max_routes * SEARCHES_PER_ROUTE {
+            if routes.len() >= options.max_routes {
+                break;
+            }
+            let blocked = (&blocked_nodes[..], &blocked_edges);
+            let mut route = match self.restricted_dijkstra(&mut state, &weights, source, dest, blocked) {
+                Some(route) => route,
+                None => break,
+            };
+            route.length = route.edges.iter().map(|&e| original[e]).sum();
+            let optimum = *optimum.get_or_insert(route.length);
+
+            let shared: Length = route
+                .edges
+                .iter()
+                .filter(|e| used.contains(*e))
+                .map(|&e| original[e])
+                .sum();
+            let overlap = if route.length == 0 {
+                1.0
+            } else {
+                shared as f64 / route.length as f64
+            };
+            let stretch = route.length as f64 / optimum.max(1) as f64;
+
+            for &edge in &route.edges {
+                weights[edge] = ((weights[edge] as f64 * options.penalty).ceil() as Length).max(
+                    weights[edge] + 1,
+                );
+            }
+            if routes.is_empty() ||
+                (overlap <= options.max_overlap && stretch <= options.max_stretch &&
+                     !routes.iter().any(|r| r.nodes == route.nodes))
+            {
+                used.extend(route.edges.iter().cloned());
+                routes.push(route);
+            }
+        }
+
+        routes.into_iter().map(Candidate::into_route).collect()
+    }
+
+    /// Dijkstra with explicit edge weights that doesn't enter blocked nodes
+    /// or use blocked edges, given as blocked nodes and blocked edge ids.
+    fn restricted_dijkstra(
+        &self,
+        state: &mut SearchState,
+        weights: &[Length],
+        source: NodeId,
+        dest: NodeId,
+        (blocked_nodes, blocked_edges): (&[bool], &HashSet<EdgeId>),
+    ) -> Option<Candidate> {
+        state.reset();
+        let SearchState {
+            ref mut dist,
+            ref mut prev_edge,
+            ref mut touched,
+            ref mut heap,
+        } = *state;
+        dist[source] = 0;
+        touched.push(source);
+        heap.push(NodeCost {
+            node: source,
+            cost: 0,
+        });
+
+        while let Some(NodeCost { node, cost }) = heap.pop() {
+            if node == dest {
+                let mut nodes = vec![dest];
+                let mut edges = Vec::new();
+                let mut cur = dest;
+                while cur != source {
+                    let edge = prev_edge[cur];
+                    edges.push(edge);
+                    cur = self.edge_source(edge);
+                    nodes.push(cur);
+                }
+                nodes.reverse();
+                edges.reverse();
+                return Some(Candidate {
+                    length: cost,
+                    nodes,
+                    edges,
+                });
+            }
+            if cost > dist[node] {
+                continue;
+            }
+            let first = self.node_offsets[node].out_start;
+            for (i, edge) in self.outgoing_edges_for(node).iter().enumerate() {
+                let id = first + i;
+                if blocked_nodes[edge.endpoint] || blocked_edges.contains(&id) {
+                    continue;
+                }
+                let next = NodeCost {
+                    node: edge.endpoint,
+                    cost: cost + weights[id],
+                };
+                if next.cost < dist[next.node] {
+                    if dist[next.node] == usize::MAX {
+                        touched.push(next.node);
+                    }
+                    dist[next.node] = next.cost;
+                    prev_edge[next.node] = id;
+                    heap.push(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Source of the out edge with the given index. Nodes without edges share
+    /// their offset with the next node, so the last node starting at or
+    /// before `edge` is the source.
+    fn edge_source(&self, edge: EdgeId) -> NodeId {
+        self.node_offsets.partition_point(|o| o.out_start <= edge) - 1
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ae1::*;
+
+    fn ladder() -> Graph {
+        // three ways from 0 to 5: 0-1-5 (10), 0-2-5 (12), 0-3-4-5 (13) and
+        // a short cut 1-2 (1)
+        let mut edges = Vec::new();
+        for &(a, b, length) in &[(0, 1, 5), (1, 5, 5), (0, 2, 6), (2, 5, 6), (0, 3, 4), (3, 4, 4), (4, 5, 5), (1, 2, 1)] {
+            edges.push(EdgeInfo::new(a, b, length, RESIDENTIAL, 30));
+            edges.push(EdgeInfo::new(b, a, length, RESIDENTIAL, 30));
+        }
+        Graph::new(
+            (0..6).map(|i| NodeInfo::new(i, 48.0, 9.0, 0)).collect(),
+            edges,
+        )
+    }
+
+    #[test]
+    fn yen() {
+        let g = ladder();
+        let routes = g.k_shortest_paths(0, 5, 5);
+        let lengths: Vec<Length> = routes.iter().map(|r| r.length).collect();
+        assert_eq!(lengths, vec![10, 12, 12, 12, 13]);
+        assert_eq!(routes[0].path, vec![0, 1, 5]);
+        assert_eq!(routes[4].path, vec![0, 3, 4, 5]);
+        for route in &routes {
+            let mut nodes = route.path.clone();
+            nodes.sort();
+            nodes.dedup();
+            assert_eq!(nodes.len(), route.path.len(), "{:?} has a loop", route);
+            assert_eq!(g.route_summary(&route.path).length, route.length);
+        }
+        assert_eq!(g.k_shortest_paths(0, 5, 100).len(), 5);
+        assert!(g.k_shortest_paths(0, 5, 0).is_empty());
+    }
+
+    #[test]
+    fn alternatives() {
+        let g = ladder();
+        let routes = g.alternative_routes(0, 5, &Default::default());
+        let paths: Vec<Vec<NodeId>> = routes.iter().map(|r| r.path.clone()).collect();
+        assert_eq!(paths, vec![vec![0, 1, 5], vec![0, 2, 5], vec![0, 3, 4, 5]]);
+
+        let strict = AlternativeOptions {
+            max_stretch: 1.25,
+            ..Default::default()
+        };
+        assert_eq!(g.alternative_routes(0, 5, &strict).len(), 2);
+    }
+}
@@ -0,0 +1,595 @@
+use ae1::*;
+
+use heapsize::HeapSizeOf;
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::slice;
+
+/// Coordinates are stored as fixed point numbers in 1e-7 degrees, like OSM
+/// does, which fits ±180° into an `i32`.
+const COORDINATE_SCALE: f64 = 10_000_000.0;
+
+/// A value of the input doesn't fit into the smaller type of `CompactGraph`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverflowError {
+    pub field: &'static str,
+    pub value: String,
+}
+
+impl fmt::Display for OverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} does not fit into the compact graph", self.field, self.value)
+    }
+}
+
+impl Error for OverflowError {}
+
+fn narrow<T: TryFrom<usize>>(value: usize, field: &'static str) -> Result<T, OverflowError> {
+    T::try_from(value).map_err(|_| {
+        OverflowError {
+            field,
+            value: value.to_string(),
+        }
+    })
+}
+
+fn fixed_point(degrees: f64, field: &'static str) -> Result<i32, OverflowError> {
+    let value = (degrees * COORDINATE_SCALE).round();
+    if value.is_finite() && value >= i32::MIN as f64 && value <= i32::MAX as f64 {
+        Ok(value as i32)
+    } else {
+        Err(OverflowError {
+            field,
+            value: degrees.to_string(),
+        })
+    }
+}
+
+#[derive(HeapSizeOf, Debug, Clone, PartialEq)]
+pub struct CompactNode {
+    pub osm_id: u64,
+    lat: i32,
+    long: i32,
+    pub height: u16,
+}
+
+impl CompactNode {
+    pub fn lat(&self) -> Latitude {
+        self.lat as f64 / COORDINATE_SCALE
+    }
+
+    pub fn long(&self) -> Longitude {
+        self.long as f64 / COORDINATE_SCALE
+    }
+}
+
+/// 12 instead of the 32 bytes of a `HalfEdge` and its speed.
+#[derive(HeapSizeOf, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactEdge {
+    pub endpoint: u32,
+    pub weight: u32,
+    pub road_type: u8,
+    pub speed: u8,
+}
+
+/// How `CompactGraph` stores its adjacency arrays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeLayout {
+    /// An array of `CompactEdge`s.
+    Plain,
+    /// Per node the endpoints as differences to the previous endpoint (to the
+    /// node itself for the first one) and the weights, both as LEB128
+    /// varints, followed by road type and speed bytes.
+    Delta,
+}
+
+/// Edges of one direction, grouped by node. `first` indexes `edges` for the
+/// plain layout and `bytes` for the delta layout.
+#[derive(HeapSizeOf)]
+struct Adjacency {
+    first: Vec<u32>,
+    edges: Vec<CompactEdge>,
+    bytes: Vec<u8>,
+}
+
+impl Adjacency {
+    /// Builds the adjacency from `(node, edge)` pairs sorted by node and
+    /// endpoint, all nodes being smaller than `node_count`.
+    fn new(
+        node_count: usize,
+        edges: &[(u32, CompactEdge)],
+        layout: EdgeLayout,
+    ) -> Result<Adjacency, OverflowError> {
+        let mut adjacency = Adjacency {
+            first: Vec::with_capacity(node_count + 1),
+            edges: Vec::new(),
+            bytes: Vec::new(),
+        };
+        let mut position = 0;
+        for node in 0..node_count {
+            adjacency.first.push(narrow(adjacency.len(layout), "edge offset")?);
+            let mut previous = node as i64;
+            while position < edges.len() && edges[position].0 as usize == node {
+                let edge = edges[position].1;
+                match layout {
+                    EdgeLayout::Plain => adjacency.edges.push(edge),
+                    EdgeLayout::Delta => {
+                        let endpoint = i64::from(edge.endpoint);
+                        write_varint(&mut adjacency.bytes, zigzag(endpoint - previous));
+                        write_varint(&mut adjacency.bytes, u64::from(edge.weight));
+                        adjacency.bytes.push(edge.road_type);
+                        adjacency.bytes.push(edge.speed);
+                        previous = endpoint;
+                    }
+                }
+                position += 1;
+            }
+        }
+        adjacency.first.push(narrow(adjacency.len(layout), "edge offset")?);
+        Ok(adjacency)
+    }
+
+    fn len(&self, layout: EdgeLayout) -> usize {
+        match layout {
+            EdgeLayout::Plain => self.edges.len(),
+            EdgeLayout::Delta => self.bytes.len(),
+        }
+    }
+
+    fn edges_for(&self, id: u32, delta: bool) -> EdgeIter<'_> {
+        let start = self.first[id as usize] as usize;
+        let end = self.first[id as usize + 1] as usize;
+        if !delta {
+            EdgeIter::Plain(self.edges[start..end].iter())
+        } else {
+            EdgeIter::Delta {
+                bytes: &self.bytes,
+                position: start,
+                end,
+                last: i64::from(id),
+            }
+        }
+    }
+}
+
+/// `Graph` with 32 bit ids and weights, and optionally delta compressed
+/// adjacency arrays.
+#[derive(HeapSizeOf)]
+pub struct CompactGraph {
+    nodes: Vec<CompactNode>,
+    out_edges: Adjacency,
+    in_edges: Adjacency,
+    delta: bool,
+}
+
+/// Appends `value` as LEB128 varint.
+pub fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/// Reads a LEB128 varint at `position` and moves `position` behind it.
+pub fn read_varint(bytes: &[u8], position: &mut usize) -> u64 {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = bytes[*position];
+        *position += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte < 0x80 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+/// Outgoing edges of a node in either layout.
+pub enum EdgeIter<'a> {
+    Plain(slice::Iter<'a, CompactEdge>),
+    Delta {
+        bytes: &'a [u8],
+        position: usize,
+        end: usize,
+        last: i64,
+    },
+}
+
+impl<'a> Iterator for EdgeIter<'a> {
+    type Item = CompactEdge;
+
+    fn next(&mut self) -> Option<CompactEdge> {
+        match *self {
+            EdgeIter::Plain(ref mut iter) => iter.next().cloned(),
+            EdgeIter::Delta {
+                bytes,
+                ref mut position,
+                end,
+                ref mut last,
+            } => {
+                if *position >= end {
+                    return None;
+                }
+                *last += unzigzag(read_varint(bytes, position));
+                let weight = read_varint(bytes, position) as u32;
+                let road_type = bytes[*position];
+                let speed = bytes[*position + 1];
+                *position += 2;
+                Some(CompactEdge {
+                    endpoint: *last as u32,
+                    weight,
+                    road_type,
+                    speed,
+                })
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+struct NodeCost {
+    node: u32,
+    cost: u32,
+}
+
+impl Ord for NodeCost {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.cmp(&self.cost)
+    }
+}
+
+impl PartialOrd for NodeCost {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl CompactGraph {
+    /// Builds the graph, failing if an id, weight, height, road type, speed
+    /// or coordinate doesn't fit into its compact type.
+    pub fn new(
+        node_info: &[NodeInfo],
+        edges: Vec<EdgeInfo>,
+        layout: EdgeLayout,
+    ) -> Result<CompactGraph, OverflowError> {
+        let mut nodes = Vec::with_capacity(node_info.len());
+        for node in node_info {
+            nodes.push(compact_node(node.osm_id, node.lat, node.long, node.height)?);
+        }
+        let mut compact_edges = Vec::with_capacity(edges.len());
+        for edge in edges {
+            compact_edges.push(compact_edge(&edge)?);
+        }
+        CompactGraph::build(nodes, compact_edges, layout)
+    }
+
+    /// Builds both adjacency arrays from `(source, edge)` pairs, reusing the
+    /// pairs for the reversed edges.
+    fn build(
+        nodes: Vec<CompactNode>,
+        mut edges: Vec<(u32, CompactEdge)>,
+        layout: EdgeLayout,
+    ) -> Result<CompactGraph, OverflowError> {
+        let node_count = nodes.len();
+        narrow::<u32>(node_count, "node count")?;
+        for &(source, edge) in &edges {
+            let (field, value) = if source as usize >= node_count {
+                ("edge source", source)
+            } else if edge.endpoint as usize >= node_count {
+                ("edge endpoint", edge.endpoint)
+            } else {
+                continue;
+            };
+            return Err(OverflowError {
+                field,
+                value: value.to_string(),
+            });
+        }
+
+        edges.sort_by_key(|&(source, edge)| (source, edge.endpoint));
+        let out_edges = Adjacency::new(node_count, &edges, layout)?;
+        for &mut (ref mut source, ref mut edge) in &mut edges {
+            ::std::mem::swap(source, &mut edge.endpoint);
+        }
+        edges.sort_by_key(|&(dest, edge)| (dest, edge.endpoint));
+        let in_edges = Adjacency::new(node_count, &edges, layout)?;
+        Ok(CompactGraph {
+            nodes,
+            out_edges,
+            in_edges,
+            delta: layout == EdgeLayout::Delta,
+        })
+    }
+
+    pub fn from_graph(graph: &Graph, layout: EdgeLayout) -> Result<CompactGraph, OverflowError> {
+        let mut edges = Vec::with_capacity(graph.out_edges.len());
+        for source in 0..graph.node_count() {
+            let speeds = graph.outgoing_speeds_for(source);
+            for (edge, &speed) in graph.outgoing_edges_for(source).iter().zip(speeds) {
+                edges.push(EdgeInfo::new(
+                    source,
+                    edge.endpoint,
+                    edge.weight,
+                    edge.road_type,
+                    speed,
+                ));
+            }
+        }
+        CompactGraph::new(&graph.node_info, edges, layout)
+    }
+
+    pub fn layout(&self) -> EdgeLayout {
+        if self.delta {
+            EdgeLayout::Delta
+        } else {
+            EdgeLayout::Plain
+        }
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn node_info(&self, id: u32) -> &CompactNode {
+        &self.nodes[id as usize]
+    }
+
+    pub fn outgoing_edges_for(&self, id: u32) -> EdgeIter<'_> {
+        self.out_edges.edges_for(id, self.delta)
+    }
+
+    /// Ingoing edges, their endpoint being the source.
+    pub fn ingoing_edges_for(&self, id: u32) -> EdgeIter<'_> {
+        self.in_edges.edges_for(id, self.delta)
+    }
+
+    /// Length of the shortest path from `source` to `dest`.
+    pub fn distance(&self, source: u32, dest: u32) -> Option<u32> {
+        let mut dist = vec![u32::MAX; self.node_count()];
+        let mut heap = BinaryHeap::new();
+        dist[source as usize] = 0;
+        heap.push(NodeCost {
+            node: source,
+            cost: 0,
+        });
+        while let Some(NodeCost { node, cost }) = heap.pop() {
+            if node == dest {
+                return Some(cost);
+            }
+            if cost > dist[node as usize] {
+                continue;
+            }
+            for edge in self.outgoing_edges_for(node) {
+                let next = NodeCost {
+                    node: edge.endpoint,
+                    cost: cost.saturating_add(edge.weight),
+                };
+                if next.cost < dist[next.node as usize] {
+                    dist[next.node as usize] = next.cost;
+                    heap.push(next);
+                }
+            }
+        }
+        None
+    }
+}
+
+fn compact_node(
+    osm_id: OsmNodeId,
+    lat: Latitude,
+    long: Longitude,
+    height: Height,
+) -> Result<CompactNode, OverflowError> {
+    Ok(CompactNode {
+        osm_id: osm_id as u64,
+        lat: fixed_point(lat, "latitude")?,
+        long: fixed_point(long, "longitude")?,
+        height: narrow(height, "height")?,
+    })
+}
+
+fn compact_edge(edge: &EdgeInfo) -> Result<(u32, CompactEdge), OverflowError> {
+    Ok((
+        narrow(edge.source, "edge source")?,
+        CompactEdge {
+            endpoint: narrow(edge.dest, "edge endpoint")?,
+            weight: narrow(edge.length, "edge length")?,
+            road_type: edge.road_type,
+            speed: narrow(edge.speed, "speed")?,
+        },
+    ))
+}
+
+/// Loads a `.graph` file directly into a `CompactGraph`. The lines are
+/// parsed straight into the compact types, so the graph is never held with
+/// `usize` fields.
+pub fn load_compact_graph<P: AsRef<Path>>(
+    file: P,
+    layout: EdgeLayout,
+) -> Result<CompactGraph, OverflowError> {
+    let mut buffer = String::new();
+    let mut file = File::open(file).expect("File could not be opened");
+    file.read_to_string(&mut buffer).expect(
+        "Could not read file",
+    );
+    parse_compact_graph(&buffer, layout)
+}
+
+fn parse_compact_graph(buffer: &str, layout: EdgeLayout) -> Result<CompactGraph, OverflowError> {
+    fn field<T: ::std::str::FromStr>(data: &mut ::std::str::Split<char>, name: &str) -> T {
+        data.next()
+            .unwrap_or_else(|| panic!("No {} found", name))
+            .parse()
+            .unwrap_or_else(|_| panic!("{} not parse-able", name))
+    }
+
+    let mut lines = buffer.lines().skip_while(|l| l.starts_with('#') || l.is_empty());
+    let node_count: usize = lines
+        .next()
+        .expect("No node count found")
+        .parse()
+        .expect("Node Count could not be parsed");
+    let edge_count: usize = lines
+        .next()
+        .expect("No edge count found")
+        .parse()
+        .expect("Edge Count could not be parsed");
+
+    let mut nodes = Vec::with_capacity(node_count.min(buffer.len()));
+    for line in lines.by_ref().take(node_count) {
+        let mut data = line.split(' ');
+        data.next(); // id is not necessary
+        let osm_id = field(&mut data, "OSM ID");
+        let lat = field(&mut data, "Latitude");
+        let long = field(&mut data, "Longitude");
+        let height = field(&mut data, "Height");
+        nodes.push(compact_node(osm_id, lat, long, height)?);
+    }
+    assert_eq!(nodes.len(), node_count, "Not enough nodes found");
+
+    let mut edges = Vec::with_capacity(edge_count.min(buffer.len()));
+    for line in lines {
+        let mut data = line.split(' ');
+        let edge = EdgeInfo::new(
+            field(&mut data, "source id"),
+            field(&mut data, "destination id"),
+            field(&mut data, "length"),
+            field(&mut data, "type"),
+            field(&mut data, "speed"),
+        );
+        edges.push(compact_edge(&edge)?);
+    }
+    CompactGraph::build(nodes, edges, layout)
+}
+
+/// Heap memory used by the graph in the layouts `Graph` and `CompactGraph`
+/// support, in bytes.
+pub fn memory_report(graph: &Graph) -> Result<Vec<(&'static str, usize)>, OverflowError> {
+    Ok(vec![
+        ("usize graph", graph.heap_size_of_children()),
+        (
+            "u32 graph",
+            CompactGraph::from_graph(graph, EdgeLayout::Plain)?.heap_size_of_children(),
+        ),
+        (
+            "u32 graph, delta compressed edges",
+            CompactGraph::from_graph(graph, EdgeLayout::Delta)?.heap_size_of_children(),
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod test {
+    use ae1::*;
+    use super::*;
+
+    fn grid() -> Graph {
+        let mut edges = Vec::new();
+        for i in 0..10 {
+            for j in 0..10 {
+                let id = i * 10 + j;
+                if j + 1 < 10 {
+                    edges.push(EdgeInfo::new(id, id + 1, 3 + i, RESIDENTIAL, 30));
+                    edges.push(EdgeInfo::new(id + 1, id, 3 + i, RESIDENTIAL, 30));
+                }
+                if i + 1 < 10 {
+                    edges.push(EdgeInfo::new(id, id + 10, 200 + j, PRIMARY, 100));
+                    edges.push(EdgeInfo::new(id + 10, id, 200 + j, PRIMARY, 100));
+                }
+            }
+        }
+        let nodes = (0..100)
+            .map(|id| NodeInfo::new(id, 48.0 + (id / 10) as f64 * 0.01, 9.0 + (id % 10) as f64 * 0.01, id))
+            .collect();
+        Graph::new(nodes, edges)
+    }
+
+    #[test]
+    fn compact_layouts() {
+        let g = grid();
+        let plain = CompactGraph::from_graph(&g, EdgeLayout::Plain).unwrap();
+        let delta = CompactGraph::from_graph(&g, EdgeLayout::Delta).unwrap();
+        assert_eq!(plain.layout(), EdgeLayout::Plain);
+        assert_eq!(delta.layout(), EdgeLayout::Delta);
+        assert_eq!(plain.node_info(57).lat(), 48.05);
+        assert_eq!(plain.node_info(57).height, 57);
+
+        for node in 0..100 {
+            let expected: Vec<CompactEdge> = g.outgoing_edges_for(node)
+                .iter()
+                .zip(g.outgoing_speeds_for(node))
+                .map(|(e, &speed)| CompactEdge {
+                    endpoint: e.endpoint as u32,
+                    weight: e.weight as u32,
+                    road_type: e.road_type,
+                    speed: speed as u8,
+                })
+                .collect();
+            assert_eq!(plain.outgoing_edges_for(node as u32).collect::<Vec<_>>(), expected);
+            assert_eq!(delta.outgoing_edges_for(node as u32).collect::<Vec<_>>(), expected);
+
+            let sources: Vec<u32> = g.ingoing_edges_for(node)
+                .iter()
+                .map(|e| e.endpoint as u32)
+                .collect();
+            for compact in &[&plain, &delta] {
+                let found: Vec<u32> = compact.ingoing_edges_for(node as u32).map(|e| e.endpoint).collect();
+                assert_eq!(found, sources);
+            }
+        }
+
+        let file = "# comment\n\n3\n2\n0 10 48.0 9.0 5\n1 11 48.1 9.1 6\n2 12 48.2 9.2 7\n\
+                    0 1 20 12 30\n2 1 10 3 100\n";
+        let parsed = super::parse_compact_graph(file, EdgeLayout::Delta).unwrap();
+        assert_eq!(parsed.node_info(2).osm_id, 12);
+        let into_1: Vec<(u32, u32)> = parsed.ingoing_edges_for(1).map(|e| (e.endpoint, e.weight)).collect();
+        assert_eq!(into_1, vec![(0, 20), (2, 10)]);
+
+        let mut dijkstra = g.dijkstra();
+        for &(s, t) in &[(0, 99), (99, 0), (45, 3), (7, 7)] {
+            let expected = dijkstra.distance(s, t).map(|(d, _)| d as u32);
+            assert_eq!(plain.distance(s as u32, t as u32), expected);
+            assert_eq!(delta.distance(s as u32, t as u32), expected);
+        }
+
+        let report = memory_report(&g).unwrap();
+        assert!(report[1].1 < report[0].1);
+        assert!(report[2].1 < report[1].1);
+    }
+
+    #[test]
+    fn overflow_is_reported() {
+        let nodes = vec![NodeInfo::new(1, 48.0, 9.0, 0), NodeInfo::new(2, 48.0, 9.0, 70_000)];
+        let error = CompactGraph::new(&nodes, Vec::new(), EdgeLayout::Plain).err();
+        assert_eq!(error.map(|e| e.field), Some("height"));
+
+        let nodes = vec![NodeInfo::new(1, 48.0, 9.0, 0), NodeInfo::new(2, 48.0, 9.0, 0)];
+        let edges = vec![EdgeInfo::new(0, 1, 1 << 40, RESIDENTIAL, 30)];
+        let error = CompactGraph::new(&nodes, edges, EdgeLayout::Delta).err();
+        assert_eq!(error.map(|e| e.field), Some("edge length"));
+
+        let edges = vec![EdgeInfo::new(0, 5, 1, RESIDENTIAL, 30)];
+        let error = CompactGraph::new(&nodes, edges, EdgeLayout::Plain).err();
+        assert_eq!(
+            error.map(|e| e.to_string()),
+            Some("edge endpoint 5 does not fit into the compact graph".to_string())
+        );
+    }
+}
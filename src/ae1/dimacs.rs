@@ -0,0 +1,179 @@
+use ae1::*;
+
+use std::path::Path;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+
+/// DIMACS coordinates are integers in millionths of a degree.
+const COORDINATE_SCALE: f64 = 1_000_000.0;
+
+fn read_file<P: AsRef<Path>>(file: P) -> String {
+    let mut buffer = String::new();
+    let mut file = File::open(file).expect("File could not be opened");
+    file.read_to_string(&mut buffer).expect(
+        "Could not read file",
+    );
+    buffer
+}
+
+/// Reads the node count from the field at `position` of the `p` line.
+fn node_count_of(buffer: &str, position: usize) -> usize {
+    buffer
+        .lines()
+        .find(|l| l.starts_with("p "))
+        .and_then(|l| l.split_whitespace().nth(position))
+        .map(str::parse)
+        .expect("No problem line found")
+        .expect("Node Count could not be parsed")
+}
+
+fn parse_dimacs(gr: &str, co: Option<&str>) -> (Vec<NodeInfo>, Vec<EdgeInfo>) {
+    let node_count = node_count_of(gr, 2);
+    let mut nodes: Vec<NodeInfo> = (0..node_count)
+        .map(|id| NodeInfo::new(id + 1, 0.0, 0.0, 0))
+        .collect();
+
+    if let Some(co) = co {
+        assert_eq!(node_count, node_count_of(co, 4), "Node counts differ");
+        for line in co.lines().filter(|l| l.starts_with("v ")) {
+            let mut raw_node_data = line.split_whitespace().skip(1);
+            let id: NodeId = raw_node_data
+                .next()
+                .map(str::parse)
+                .expect("No Node ID Data")
+                .expect("Node ID not parse-able");
+            let x: i64 = raw_node_data
+                .next()
+                .map(str::parse)
+                .expect("No Longitude Data")
+                .expect("Longitude not parse-able");
+            let y: i64 = raw_node_data
+                .next()
+                .map(str::parse)
+                .expect("No Latitude Data")
+                .expect("Latitude not parse-able");
+            let node = &mut nodes[id - 1];
+            node.long = x as f64 / COORDINATE_SCALE;
+            node.lat = y as f64 / COORDINATE_SCALE;
+        }
+    }
+
+    let edges = gr.lines()
+        .filter(|l| l.starts_with("a "))
+        .map(|l| {
+            let mut raw_edge_data = l.split_whitespace().skip(1);
+            let source: NodeId = raw_edge_data
+                .next()
+                .map(str::parse)
+                .expect("No source Id found")
+                .expect("Source id not parse-able");
+            let dest: NodeId = raw_edge_data
+                .next()
+                .map(str::parse)
+                .expect("No destination Id found")
+                .expect("Destination id not parse-able");
+            let length: Length = raw_edge_data
+                .next()
+                .map(str::parse)
+                .expect("No length found")
+                .expect("Length not parse-able");
+            EdgeInfo::new(source - 1, dest - 1, length, 0, 0)
+        })
+        .collect();
+
+    (nodes, edges)
+}
+
+/// Loads a graph in the DIMACS 9th challenge format. The `.co` file with
+/// coordinates is optional. The DIMACS node id is kept as `osm_id`; speeds,
+/// heights and road types are not part of the format and are set to 0.
+pub fn load_dimacs<P: AsRef<Path>, Q: AsRef<Path>>(gr_file: P, co_file: Option<Q>) -> Graph {
+    let gr = read_file(gr_file);
+    let co = co_file.map(read_file);
+    let (nodes, edges) = parse_dimacs(&gr, co.as_deref());
+    Graph::new(nodes, edges)
+}
+
+/// Writes the graph as DIMACS 9th challenge `.gr` and `.co` files.
+pub fn write_dimacs<P: AsRef<Path>, Q: AsRef<Path>>(
+    graph: &Graph,
+    gr_file: P,
+    co_file: Q,
+) -> io::Result<()> {
+    let mut gr = BufWriter::new(File::create(gr_file)?);
+    write_gr(graph, &mut gr)?;
+    gr.flush()?;
+
+    let mut co = BufWriter::new(File::create(co_file)?);
+    write_co(graph, &mut co)?;
+    co.flush()
+}
+
+fn write_gr<W: Write>(graph: &Graph, writer: &mut W) -> io::Result<()> {
+    writeln!(writer, "c travel distance graph")?;
+    writeln!(
+        writer,
+        "p sp {} {}",
+        graph.node_info.len(),
+        graph.out_edges.len()
+    )?;
+    for source in 0..graph.node_info.len() {
+        for edge in graph.outgoing_edges_for(source) {
+            writeln!(writer, "a {} {} {}", source + 1, edge.endpoint + 1, edge.weight)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_co<W: Write>(graph: &Graph, writer: &mut W) -> io::Result<()> {
+    writeln!(writer, "c coordinates in millionths of a degree")?;
+    writeln!(writer, "p aux sp co {}", graph.node_info.len())?;
+    for (id, node) in graph.node_info.iter().enumerate() {
+        writeln!(
+            writer,
+            "v {} {} {}",
+            id + 1,
+            (node.long * COORDINATE_SCALE).round() as i64,
+            (node.lat * COORDINATE_SCALE).round() as i64
+        )?;
+    }
+    Ok(())
+}
+
+#[test]
+fn dimacs_round_trip() {
+    let g = Graph::new(
+        vec![
+            NodeInfo::new(23, 48.745312, 9.105921, 12),
+            NodeInfo::new(27, 48.746145, -9.106337, 13),
+            NodeInfo::new(53, 48.747011, 9.107006, 14),
+        ],
+        vec![
+            EdgeInfo::new(0, 1, 10, 3, 30),
+            EdgeInfo::new(1, 2, 12, 3, 50),
+            EdgeInfo::new(2, 0, 15, 3, 50),
+        ],
+    );
+    let mut gr = Vec::new();
+    let mut co = Vec::new();
+    write_gr(&g, &mut gr).unwrap();
+    write_co(&g, &mut co).unwrap();
+    let gr = String::from_utf8(gr).unwrap();
+    let co = String::from_utf8(co).unwrap();
+    assert!(gr.contains("p sp 3 3\n"));
+    assert!(co.contains("v 2 -9106337 48746145\n"));
+
+    let (nodes, edges) = parse_dimacs(&gr, Some(&co));
+    assert_eq!(nodes.len(), 3);
+    assert_eq!(nodes[1].osm_id, 2);
+    assert_eq!(nodes[1].lat, 48.746145);
+    assert_eq!(nodes[1].long, -9.106337);
+    assert_eq!(
+        edges,
+        vec![
+            EdgeInfo::new(0, 1, 10, 0, 0),
+            EdgeInfo::new(1, 2, 12, 0, 0),
+            EdgeInfo::new(2, 0, 15, 0, 0),
+        ]
+    );
+}
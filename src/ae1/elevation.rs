@@ -0,0 +1,80 @@
+use ae1::*;
+
+/// Length and elevation changes along a path.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RouteSummary {
+    pub length: Length,
+    pub ascent: Height,
+    pub descent: Height,
+}
+
+impl Graph {
+    /// Ascent and descent in meters when following `edge` from `source`.
+    pub fn climb(&self, source: NodeId, edge: &HalfEdge) -> (Height, Height) {
+        let from = self.node_info[source].height;
+        let to = self.node_info[edge.endpoint].height;
+        if to > from { (to - from, 0) } else { (0, from - to) }
+    }
+
+    /// Sums up length, ascent and descent along a path as returned by
+    /// `Dijkstra::distance`. Between consecutive nodes the shortest edge is
+    /// used; panics if two consecutive nodes are not connected.
+    pub fn route_summary<'p, I>(&self, path: I) -> RouteSummary
+    where
+        I: IntoIterator<Item = &'p NodeId>,
+    {
+        let mut summary = RouteSummary::default();
+        let mut nodes = path.into_iter();
+        let mut prev = match nodes.next() {
+            Some(&node) => node,
+            None => return summary,
+        };
+        for &node in nodes {
+            let edge = self.outgoing_edges_for(prev)
+                .iter()
+                .filter(|e| e.endpoint == node)
+                .min_by_key(|e| e.weight)
+                .expect("path contains nodes which are not connected");
+            let (ascent, descent) = self.climb(prev, edge);
+            summary.length += edge.weight;
+            summary.ascent += ascent;
+            summary.descent += descent;
+            prev = node;
+        }
+        summary
+    }
+}
+
+#[test]
+fn climb_along_route() {
+    let g = Graph::new(
+        vec![
+            NodeInfo::new(1, 48.0, 9.0, 300),
+            NodeInfo::new(2, 48.1, 9.0, 420),
+            NodeInfo::new(3, 48.2, 9.0, 310),
+            NodeInfo::new(4, 48.1, 9.1, 300),
+        ],
+        vec![
+            EdgeInfo::new(0, 1, 100, RESIDENTIAL, 30),
+            EdgeInfo::new(1, 2, 100, RESIDENTIAL, 30),
+            EdgeInfo::new(0, 3, 150, RESIDENTIAL, 30),
+            EdgeInfo::new(3, 2, 150, RESIDENTIAL, 30),
+        ],
+    );
+    let mut d = g.dijkstra();
+    let (dist, path) = d.distance(0, 2).unwrap();
+    assert_eq!(dist, 200);
+    assert_eq!(
+        g.route_summary(&path),
+        RouteSummary {
+            length: 200,
+            ascent: 120,
+            descent: 110,
+        }
+    );
+
+    let (dist, path) = d.distance_with(0, 2, &QueryOptions::bicycle()).unwrap();
+    assert_eq!(dist, 300 + 10 * 8);
+    assert_eq!(path, vec![0, 3, 2]);
+    assert_eq!(g.route_summary(&path).ascent, 10);
+}
@@ -0,0 +1,326 @@
+use ae1::*;
+use super::reverse::Direction;
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// Planar point as (longitude, latitude).
+pub type Point = (Longitude, Latitude);
+
+/// Edge leaving the reachable area; `reached` is the fraction of its length
+/// that can still be driven within the budget.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundaryEdge {
+    pub source: NodeId,
+    pub dest: NodeId,
+    pub reached: f64,
+}
+
+/// Result of a bounded Dijkstra: every node reachable within the budget with
+/// its distance, and the edges where the budget runs out.
+#[derive(Debug, Default)]
+pub struct Isochrone {
+    pub nodes: Vec<(NodeId, Length)>,
+    pub boundary: Vec<BoundaryEdge>,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+struct NodeCost {
+    node: NodeId,
+    cost: usize,
+}
+
+impl Ord for NodeCost {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.cmp(&self.cost)
+    }
+}
+
+impl PartialOrd for NodeCost {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Graph {
+    /// All nodes with a distance of at most `budget` from `source`.
+    pub fn isochrone(&self, source: NodeId, budget: Length) -> Isochrone {
+        self.isochrone_in(source, budget, Direction::Forward)
+    }
+
+    /// All nodes from which `target` can be reached within `budget`. The
+    /// `source` of boundary edges is the node inside the area here as well.
+    pub fn reverse_isochrone(&self, target: NodeId, budget: Length) -> Isochrone {
+        self.isochrone_in(target, budget, Direction::Backward)
+    }
+
+    fn isochrone_in(&self, source: NodeId, budget: Length, direction: Direction) -> Isochrone {
+        let mut dist = vec![usize::MAX; self.node_count()];
+        let mut heap = BinaryHeap::new();
+        let mut result = Isochrone::default();
+        dist[source] = 0;
+        heap.push(NodeCost {
+            node: source,
+            cost: 0,
+        });
+
+        while let Some(NodeCost { node, cost }) = heap.pop() {
+            if cost > dist[node] {
+                continue;
+            }
+            result.nodes.push((node, cost));
+            for edge in self.edges_in_direction(node, direction) {
+                let next = NodeCost {
+                    node: edge.endpoint,
+                    cost: cost + edge.weight,
+                };
+                if next.cost > budget {
+                    result.boundary.push(BoundaryEdge {
+                        source: node,
+                        dest: edge.endpoint,
+                        reached: (budget - cost) as f64 / edge.weight as f64,
+                    });
+                } else if next.cost < dist[next.node] {
+                    dist[next.node] = next.cost;
+                    heap.push(next);
+                }
+            }
+        }
+
+        // boundary edges to nodes which turned out to be reachable another way
+        // are completely inside
+        result.boundary.retain(|e| dist[e.dest] == usize::MAX);
+        result
+    }
+
+    /// Coordinates of all reachable nodes and of the points where the budget
+    /// runs out on boundary edges.
+    pub fn isochrone_points(&self, isochrone: &Isochrone) -> Vec<Point> {
+        let position = |id: NodeId| (self.node_info[id].long, self.node_info[id].lat);
+        let mut points: Vec<Point> = isochrone.nodes.iter().map(|&(id, _)| position(id)).collect();
+        for edge in &isochrone.boundary {
+            let (x1, y1) = position(edge.source);
+            let (x2, y2) = position(edge.dest);
+            points.push((
+                x1 + (x2 - x1) * edge.reached,
+                y1 + (y2 - y1) * edge.reached,
+            ));
+        }
+        points
+    }
+}
+
+fn cross(o: Point, a: Point, b: Point) -> f64 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+/// Distance of `p` to the segment from `a` to `b`.
+fn segment_distance(p: Point, a: Point, b: Point) -> f64 {
+    let length = (b.0 - a.0).powi(2) + (b.1 - a.1).powi(2);
+    if length == 0.0 {
+        return distance(p, a);
+    }
+    let t = (((p.0 - a.0) * (b.0 - a.0) + (p.1 - a.1) * (b.1 - a.1)) / length).clamp(0.0, 1.0);
+    distance(p, (a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1)))
+}
+
+/// Whether the segments a-b and c-d cross, touching endpoints excluded.
+fn segments_cross(a: Point, b: Point, c: Point, d: Point) -> bool {
+    if a == c || a == d || b == c || b == d {
+        return false;
+    }
+    let d1 = cross(c, d, a);
+    let d2 = cross(c, d, b);
+    let d3 = cross(a, b, c);
+    let d4 = cross(a, b, d);
+    ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0)) &&
+        ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+}
+
+/// Indices of the convex hull in counter clockwise order (monotone chain).
+fn convex_hull(points: &[Point]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    order.sort_by(|&a, &b| {
+        points[a].partial_cmp(&points[b]).unwrap_or(Ordering::Equal)
+    });
+    order.dedup_by(|a, b| points[*a] == points[*b]);
+    if order.len() < 3 {
+        return order;
+    }
+
+    let mut hull: Vec<usize> = Vec::with_capacity(2 * order.len());
+    for pass in 0..2 {
+        let start = hull.len();
+        let iter: Box<dyn Iterator<Item = &usize>> = if pass == 0 {
+            Box::new(order.iter())
+        } else {
+            Box::new(order.iter().rev())
+        };
+        for &i in iter {
+            while hull.len() >= start + 2 &&
+                cross(points[hull[hull.len() - 2]], points[hull[hull.len() - 1]], points[i]) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(i);
+        }
+        hull.pop();
+    }
+    hull
+}
+
+/// Concave hull of `points` by digging into the edges of the convex hull
+/// (Park and Oh). An edge is replaced by two edges through the nearest inner
+/// point while the edge is more than `threshold` times longer than the
+/// distance of that point to the edge's endpoints. Larger thresholds give
+/// smoother polygons, `f64::INFINITY` the convex hull.
+pub fn concave_hull(points: &[Point], threshold: f64) -> Vec<Point> {
+    let mut hull = convex_hull(points);
+    if hull.len() < 3 {
+        return hull.into_iter().map(|i| points[i]).collect();
+    }
+    let mut inside = vec![true; points.len()];
+    for &i in &hull {
+        inside[i] = false;
+    }
+    for i in 0..points.len() {
+        if inside[i] && hull.iter().any(|&h| points[h] == points[i]) {
+            inside[i] = false;
+        }
+    }
+
+    let mut edge = 0;
+    while edge < hull.len() {
+        let n = hull.len();
+        let before = points[hull[(edge + n - 1) % n]];
+        let a = points[hull[edge]];
+        let b = points[hull[(edge + 1) % n]];
+        let after = points[hull[(edge + 2) % n]];
+        // only points closer to this edge than to its neighbors are candidates
+        let nearest = (0..points.len())
+            .filter(|&i| inside[i])
+            .map(|i| (segment_distance(points[i], a, b), i))
+            .filter(|&(d, i)| {
+                d <= segment_distance(points[i], before, a) &&
+                    d <= segment_distance(points[i], b, after)
+            })
+            .min_by(|x, y| x.partial_cmp(y).unwrap_or(Ordering::Equal));
+
+        let dig = nearest.and_then(|(_, i)| {
+            let p = points[i];
+            let decision = distance(p, a).min(distance(p, b));
+            if decision == 0.0 || distance(a, b) / decision <= threshold {
+                return None;
+            }
+            let crosses = (0..hull.len()).any(|j| {
+                let c = points[hull[j]];
+                let d = points[hull[(j + 1) % hull.len()]];
+                j != edge && (segments_cross(a, p, c, d) || segments_cross(p, b, c, d))
+            });
+            if crosses { None } else { Some(i) }
+        });
+
+        match dig {
+            Some(i) => {
+                hull.insert(edge + 1, i);
+                inside[i] = false;
+            }
+            None => edge += 1,
+        }
+    }
+
+    hull.into_iter().map(|i| points[i]).collect()
+}
+
+/// GeoJSON feature with the polygon, closing the ring if necessary.
+pub fn polygon_to_geojson(polygon: &[Point]) -> String {
+    let mut ring: Vec<String> = polygon
+        .iter()
+        .map(|&(long, lat)| format!("[{},{}]", long, lat))
+        .collect();
+    if let (Some(first), Some(last)) = (polygon.first(), polygon.last()) {
+        if first != last {
+            ring.push(ring[0].clone());
+        }
+    }
+    format!(
+        "{{\"type\":\"Feature\",\"properties\":{{}},\"geometry\":{{\"type\":\"Polygon\",\"coordinates\":[[{}]]}}}}",
+        ring.join(",")
+    )
+}
+
+#[test]
+fn isochrone_boundary() {
+    let g = Graph::new(
+        vec![
+            NodeInfo::new(1, 48.0, 9.0, 0),
+            NodeInfo::new(2, 48.0, 9.1, 0),
+            NodeInfo::new(3, 48.0, 9.2, 0),
+            NodeInfo::new(4, 48.1, 9.0, 0),
+        ],
+        vec![
+            EdgeInfo::new(0, 1, 10, RESIDENTIAL, 30),
+            EdgeInfo::new(1, 2, 10, RESIDENTIAL, 30),
+            EdgeInfo::new(0, 3, 40, RESIDENTIAL, 30),
+            EdgeInfo::new(2, 3, 5, RESIDENTIAL, 30),
+        ],
+    );
+    let iso = g.isochrone(0, 15);
+    assert_eq!(iso.nodes, vec![(0, 0), (1, 10)]);
+    assert_eq!(iso.boundary.len(), 2);
+    assert!(iso.boundary.contains(&BoundaryEdge {
+        source: 1,
+        dest: 2,
+        reached: 0.5,
+    }));
+    let points = g.isochrone_points(&iso);
+    assert_eq!(points.len(), 4);
+    assert!((points[2].0 - 9.15).abs() < 1e-9 || (points[3].0 - 9.15).abs() < 1e-9);
+
+    let iso = g.isochrone(0, 25);
+    assert_eq!(iso.nodes, vec![(0, 0), (1, 10), (2, 20), (3, 25)]);
+    assert!(iso.boundary.is_empty());
+
+    let iso = g.reverse_isochrone(3, 12);
+    assert_eq!(iso.nodes, vec![(3, 0), (2, 5)]);
+    assert_eq!(
+        iso.boundary,
+        vec![
+            BoundaryEdge {
+                source: 3,
+                dest: 0,
+                reached: 0.3,
+            },
+            BoundaryEdge {
+                source: 2,
+                dest: 1,
+                reached: 0.7,
+            },
+        ]
+    );
+}
+
+#[test]
+fn concave_hull_of_notch() {
+    let points = vec![(0.0, 0.0), (2.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0), (2.0, 3.0)];
+
+    let convex = concave_hull(&points, f64::INFINITY);
+    assert_eq!(convex, vec![(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0)]);
+    assert_eq!(concave_hull(&points, 1.9).len(), 5);
+
+    let concave = concave_hull(&points, 1.5);
+    assert_eq!(
+        concave,
+        vec![(0.0, 0.0), (2.0, 0.0), (4.0, 0.0), (4.0, 4.0), (2.0, 3.0), (0.0, 4.0)]
+    );
+
+    let json = polygon_to_geojson(&convex);
+    assert_eq!(
+        json,
+        "{\"type\":\"Feature\",\"properties\":{},\"geometry\":{\"type\":\"Polygon\",\"coordinates\":[[[0,0],[4,0],[4,4],[0,4],[0,0]]]}}"
+    );
+}
@@ -12,6 +12,10 @@ fn load_file<P: AsRef<Path>>(file: P) -> (Vec<NodeInfo>, Vec<EdgeInfo>) {
     file.read_to_string(&mut buffer).expect(
         "Could not read file",
     );
+    parse_graph(&buffer)
+}
+
+pub(super) fn parse_graph(buffer: &str) -> (Vec<NodeInfo>, Vec<EdgeInfo>) {
     let lines: Vec<&str> = buffer
         .lines()
         .skip_while(|l| l.starts_with('#') || l.is_empty())
@@ -66,13 +70,17 @@ fn load_file<P: AsRef<Path>>(file: P) -> (Vec<NodeInfo>, Vec<EdgeInfo>) {
                 .map(str::parse)
                 .expect("No length found")
                 .expect("Length id not parse-able");
-            raw_node_data.next(); //ignore type
+            let road_type: RoadType = raw_node_data
+                .next()
+                .map(str::parse)
+                .expect("No type found")
+                .expect("Type not parse-able");
             let speed: Speed = raw_node_data
                 .next()
                 .map(str::parse)
                 .expect("No speed found")
                 .expect("Speed id not parse-able");
-            EdgeInfo::new(source, dest, length, speed)
+            EdgeInfo::new(source, dest, length, road_type, speed)
 
         })
         .collect();
@@ -1,17 +1,61 @@
 mod load;
 mod alg;
+mod osm;
+mod dimacs;
+mod write;
+mod subgraph;
+mod elevation;
+mod turn;
+mod update;
+mod alternatives;
+mod isochrone;
+mod reverse;
+mod validate;
+mod reorder;
+mod compact;
 
 pub use self::load::load_graph;
+pub use self::osm::load_osm_xml;
+pub use self::dimacs::{load_dimacs, write_dimacs};
+pub use self::write::write_graph;
+pub use self::alg::QueryOptions;
+pub use self::elevation::RouteSummary;
+pub use self::alternatives::{AlternativeOptions, Route};
+pub use self::isochrone::{concave_hull, polygon_to_geojson};
+pub use self::reverse::Direction;
+pub use self::reorder::{NodeMapping, NodeOrder};
+pub use self::compact::{CompactGraph, EdgeLayout, load_compact_graph, memory_report};
+pub use self::compact::{read_varint, write_varint};
+pub use self::validate::{GraphStats, check_graph_file};
+pub use self::turn::{EdgeGraph, TurnRestriction, load_turn_restrictions};
 
 pub type NodeId = usize;
+pub type EdgeId = usize;
 pub type OsmNodeId = usize;
 pub type Latitude = f64;
 pub type Longitude = f64;
 pub type Length = usize;
 pub type Speed = usize;
 pub type Height = usize;
-
-#[derive(HeapSizeOf)]
+pub type RoadType = u8;
+
+pub const MOTORWAY: RoadType = 1;
+pub const TRUNK: RoadType = 2;
+pub const PRIMARY: RoadType = 3;
+pub const SECONDARY: RoadType = 4;
+pub const TERTIARY: RoadType = 5;
+pub const MOTORWAY_LINK: RoadType = 6;
+pub const TRUNK_LINK: RoadType = 7;
+pub const PRIMARY_LINK: RoadType = 8;
+pub const SECONDARY_LINK: RoadType = 9;
+pub const ROAD: RoadType = 10;
+pub const UNCLASSIFIED: RoadType = 11;
+pub const RESIDENTIAL: RoadType = 12;
+pub const UNSURFACED: RoadType = 13;
+pub const LIVING_STREET: RoadType = 14;
+pub const SERVICE: RoadType = 15;
+
+#[derive(Clone, HeapSizeOf)]
 pub struct NodeInfo {
     osm_id: OsmNodeId,
     lat: Latitude,
@@ -20,7 +64,15 @@ pub struct NodeInfo {
 }
 
 impl NodeInfo {
-    fn new(osm_id: OsmNodeId, lat: Latitude, long: Longitude, height: Height) -> NodeInfo {
+    pub fn lat(&self) -> Latitude {
+        self.lat
+    }
+
+    pub fn long(&self) -> Longitude {
+        self.long
+    }
+
+    pub fn new(osm_id: OsmNodeId, lat: Latitude, long: Longitude, height: Height) -> NodeInfo {
         NodeInfo {
             osm_id: osm_id,
             lat: lat,
@@ -30,20 +82,58 @@ impl NodeInfo {
     }
 }
 
+/// Axis parallel rectangle of coordinates, borders included.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BoundingBox {
+    pub min_lat: Latitude,
+    pub max_lat: Latitude,
+    pub min_long: Longitude,
+    pub max_long: Longitude,
+}
+
+impl BoundingBox {
+    pub fn new(
+        min_lat: Latitude,
+        max_lat: Latitude,
+        min_long: Longitude,
+        max_long: Longitude,
+    ) -> BoundingBox {
+        BoundingBox {
+            min_lat,
+            max_lat,
+            min_long,
+            max_long,
+        }
+    }
+
+    pub fn contains(&self, lat: Latitude, long: Longitude) -> bool {
+        self.min_lat <= lat && lat <= self.max_lat && self.min_long <= long &&
+            long <= self.max_long
+    }
+}
+
 #[derive(PartialEq, Debug, HeapSizeOf)]
 pub struct EdgeInfo {
     source: NodeId,
     dest: NodeId,
     length: Length,
+    road_type: RoadType,
     speed: Speed,
 }
 
 impl EdgeInfo {
-    fn new(source: NodeId, dest: NodeId, length: Length, speed: Speed) -> EdgeInfo {
+    pub fn new(
+        source: NodeId,
+        dest: NodeId,
+        length: Length,
+        road_type: RoadType,
+        speed: Speed,
+    ) -> EdgeInfo {
         EdgeInfo {
             source: source,
             dest: dest,
             length: length,
+            road_type: road_type,
             speed: speed,
         }
     }
@@ -53,6 +143,23 @@ impl EdgeInfo {
 pub struct HalfEdge {
     endpoint: NodeId,
     weight: Length,
+    road_type: RoadType,
+}
+
+impl HalfEdge {
+    pub fn endpoint(&self) -> NodeId {
+        self.endpoint
+    }
+
+    pub fn weight(&self) -> Length {
+        self.weight
+    }
+
+    /// The road type as given in the `type` column of the graph file.
+    #[allow(dead_code)]
+    pub fn road_type(&self) -> RoadType {
+        self.road_type
+    }
 }
 
 
@@ -77,6 +184,9 @@ pub struct Graph {
     node_offsets: Vec<NodeOffset>,
     out_edges: Vec<HalfEdge>,
     in_edges: Vec<HalfEdge>,
+    /// Speed of every outgoing edge. Searches don't need it, so it is kept
+    /// out of the half edges.
+    out_speeds: Vec<Speed>,
 }
 
 enum OffsetMode {
@@ -94,6 +204,7 @@ impl Graph {
             }
         });
 
+        let out_speeds = edges.iter().map(|e| e.speed).collect();
         let node_count = node_info.len();
         let (node_offset, in_edges, out_edges) = Graph::calc_node_offsets(node_count, edges);
         Graph {
@@ -101,6 +212,7 @@ impl Graph {
             node_offsets: node_offset,
             out_edges: out_edges,
             in_edges: in_edges,
+            out_speeds: out_speeds,
         }
 
     }
@@ -109,9 +221,13 @@ impl Graph {
         &self.out_edges[self.node_offsets[id].out_start..self.node_offsets[id + 1].out_start]
     }
 
-    #[allow(dead_code)]
+    /// Speeds of the edges `outgoing_edges_for` returns, in the same order.
+    pub fn outgoing_speeds_for(&self, id: NodeId) -> &[Speed] {
+        &self.out_speeds[self.node_offsets[id].out_start..self.node_offsets[id + 1].out_start]
+    }
+
     pub fn ingoing_edges_for(&self, id: NodeId) -> &[HalfEdge] {
-        &self.out_edges[self.node_offsets[id].in_start..self.node_offsets[id + 1].in_start]
+        &self.in_edges[self.node_offsets[id].in_start..self.node_offsets[id + 1].in_start]
     }
 
     fn calc_node_offsets(
@@ -194,6 +310,7 @@ impl Graph {
                         HalfEdge {
                             endpoint: e.source,
                             weight: e.length,
+                            road_type: e.road_type,
                         }
                     })
                     .collect()
@@ -206,6 +323,7 @@ impl Graph {
                         HalfEdge {
                             endpoint: e.dest,
                             weight: e.length,
+                            road_type: e.road_type,
                         }
                     })
                     .collect()
@@ -215,7 +333,17 @@ impl Graph {
     }
 
     pub fn node_count(&self) -> usize {
-        self.node_offsets.len()
+        self.node_info.len()
+    }
+
+    pub fn node_info(&self, id: NodeId) -> &NodeInfo {
+        &self.node_info[id]
+    }
+
+    /// Weights of all edges, indexed by the position of the edge when
+    /// iterating `outgoing_edges_for` over all nodes in order.
+    pub fn edge_weights(&self) -> Vec<Length> {
+        self.out_edges.iter().map(|e| e.weight).collect()
     }
 }
 
@@ -230,11 +358,11 @@ fn graph_creation() {
             NodeInfo::new(78, 9.2, 2.3, 12),
         ],
         vec![
-            EdgeInfo::new(0, 1, 1, 1),
-            EdgeInfo::new(0, 2, 1, 1),
-            EdgeInfo::new(2, 3, 1, 1),
-            EdgeInfo::new(0, 3, 1, 1),
-            EdgeInfo::new(2, 4, 1, 1),
+            EdgeInfo::new(0, 1, 1, 1, 1),
+            EdgeInfo::new(0, 2, 1, 1, 1),
+            EdgeInfo::new(2, 3, 1, 1, 1),
+            EdgeInfo::new(0, 3, 1, 1, 1),
+            EdgeInfo::new(2, 4, 1, 1, 1),
         ],
     );
     let exp = vec![
@@ -255,10 +383,12 @@ fn graph_creation() {
             HalfEdge {
                 endpoint: 3,
                 weight: 1,
+                road_type: 1,
             },
             HalfEdge {
                 endpoint: 4,
                 weight: 1,
+                road_type: 1,
             },
         ]
     );
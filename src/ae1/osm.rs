@@ -0,0 +1,397 @@
+use ae1::*;
+
+use std::path::Path;
+use std::fs::File;
+use std::io::Read;
+use std::collections::HashMap;
+use std::borrow::Cow;
+
+const EARTH_RADIUS: f64 = 6_371_000.0;
+
+/// Great circle distance in meters between two coordinates given in degrees.
+pub fn haversine(lat_a: Latitude, long_a: Longitude, lat_b: Latitude, long_b: Longitude) -> f64 {
+    let d_lat = (lat_b - lat_a).to_radians();
+    let d_long = (long_b - long_a).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2) +
+        lat_a.to_radians().cos() * lat_b.to_radians().cos() * (d_long / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * a.sqrt().asin()
+}
+
+/// Road type and default speed in km/h for a routable highway tag, `None` for
+/// ways cars can't use.
+fn road_class(highway: &str) -> Option<(RoadType, Speed)> {
+    let class = match highway {
+        "motorway" => (MOTORWAY, 130),
+        "motorway_link" => (MOTORWAY_LINK, 80),
+        "trunk" => (TRUNK, 100),
+        "trunk_link" => (TRUNK_LINK, 70),
+        "primary" => (PRIMARY, 100),
+        "primary_link" => (PRIMARY_LINK, 60),
+        "secondary" => (SECONDARY, 70),
+        "secondary_link" => (SECONDARY_LINK, 50),
+        "tertiary" => (TERTIARY, 70),
+        // the graph format has no type for tertiary links, so they keep the
+        // tertiary type and only get the lower link speed
+        "tertiary_link" => (TERTIARY, 40),
+        "road" => (ROAD, 50),
+        "unclassified" => (UNCLASSIFIED, 50),
+        "residential" => (RESIDENTIAL, 30),
+        "track" => (UNSURFACED, 15),
+        "living_street" => (LIVING_STREET, 7),
+        "service" => (SERVICE, 20),
+        _ => return None,
+    };
+    Some(class)
+}
+
+#[derive(Debug, PartialEq)]
+enum Direction {
+    Both,
+    Forward,
+    Backward,
+}
+
+#[derive(Debug)]
+struct OsmWay {
+    nodes: Vec<OsmNodeId>,
+    road_type: RoadType,
+    speed: Speed,
+    direction: Direction,
+}
+
+impl OsmWay {
+    fn from_tags(nodes: Vec<OsmNodeId>, tags: &HashMap<String, String>) -> Option<OsmWay> {
+        let highway = tags.get("highway")?;
+        let (road_type, mut speed) = road_class(highway)?;
+        if let Some(max) = tags.get("maxspeed").and_then(|m| m.trim().parse().ok()) {
+            speed = max;
+        }
+        let implied_oneway = highway == "motorway" || highway == "motorway_link" ||
+            tags.get("junction").is_some_and(|j| j == "roundabout");
+        let direction = match tags.get("oneway").map(String::as_str) {
+            Some("yes") | Some("true") | Some("1") => Direction::Forward,
+            Some("-1") | Some("reverse") => Direction::Backward,
+            Some("no") | Some("false") | Some("0") => Direction::Both,
+            _ if implied_oneway => Direction::Forward,
+            _ => Direction::Both,
+        };
+        Some(OsmWay {
+            nodes,
+            road_type,
+            speed,
+            direction,
+        })
+    }
+}
+
+/// Replaces the predefined XML entities and character references like
+/// `&#223;` in an attribute value. Unknown entities are kept as they are.
+fn decode_entities(value: &str) -> Cow<'_, str> {
+    if !value.contains('&') {
+        return Cow::Borrowed(value);
+    }
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest.find(';').map(|end| (&rest[1..end], end));
+        let c = entity.and_then(|(name, _)| match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ if name.starts_with("#x") => {
+                u32::from_str_radix(&name[2..], 16).ok().and_then(::std::char::from_u32)
+            }
+            _ if name.starts_with('#') => name[1..].parse().ok().and_then(::std::char::from_u32),
+            _ => None,
+        });
+        match (c, entity) {
+            (Some(c), Some((_, end))) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    Cow::Owned(decoded)
+}
+
+/// One `<...>` element of the XML file with its attributes.
+struct Element<'a> {
+    name: &'a str,
+    attributes: Vec<(&'a str, Cow<'a, str>)>,
+    closing: bool,
+    self_closing: bool,
+}
+
+impl<'a> Element<'a> {
+    fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.iter().find(|&&(k, _)| k == key).map(
+            |(_, v)| v.as_ref(),
+        )
+    }
+
+    fn parse(raw: &'a str) -> Element<'a> {
+        let closing = raw.starts_with('/');
+        let self_closing = raw.ends_with('/');
+        let raw = raw.trim_matches('/');
+        let name_end = raw.find(char::is_whitespace).unwrap_or(raw.len());
+        let name = &raw[..name_end];
+
+        let mut attributes = Vec::new();
+        let mut rest = &raw[name_end..];
+        while let Some(eq) = rest.find('=') {
+            let key = rest[..eq].trim();
+            let value_part = rest[eq + 1..].trim_start();
+            let quote = match value_part.chars().next() {
+                Some(q @ '"') | Some(q @ '\'') => q,
+                _ => break,
+            };
+            let value_end = match value_part[1..].find(quote) {
+                Some(end) => end + 1,
+                None => break,
+            };
+            attributes.push((key, decode_entities(&value_part[1..value_end])));
+            rest = &value_part[value_end + 1..];
+        }
+
+        Element {
+            name,
+            attributes,
+            closing,
+            self_closing,
+        }
+    }
+}
+
+/// Iterates over all elements of an XML document, skipping text, comments and
+/// processing instructions.
+fn elements<'a>(xml: &'a str) -> Vec<Element<'a>> {
+    let mut result = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let mut quote = None;
+        let mut end = rest.len();
+        for (i, c) in rest.char_indices() {
+            match (quote, c) {
+                (None, '"') | (None, '\'') => quote = Some(c),
+                (Some(q), c) if q == c => quote = None,
+                (None, '>') => {
+                    end = i;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let raw = rest[..end].trim();
+        rest = &rest[(end + 1).min(rest.len())..];
+        if raw.starts_with('?') || raw.starts_with('!') {
+            continue;
+        }
+        result.push(Element::parse(raw));
+    }
+    result
+}
+
+fn parse_osm_xml(xml: &str) -> (Vec<NodeInfo>, Vec<EdgeInfo>) {
+    let mut coordinates: Vec<(OsmNodeId, Latitude, Longitude)> = Vec::new();
+    let mut ways = Vec::new();
+
+    let mut way_nodes = Vec::new();
+    let mut way_tags = HashMap::new();
+    let mut in_way = false;
+    for element in elements(xml) {
+        match (element.name, element.closing) {
+            ("node", false) => {
+                let id = element
+                    .attribute("id")
+                    .map(str::parse)
+                    .expect("No OSM ID Data")
+                    .expect("OSM ID not parse-able");
+                let lat = element
+                    .attribute("lat")
+                    .map(str::parse)
+                    .expect("No Latitude Data")
+                    .expect("Latitude not parse-able");
+                let long = element
+                    .attribute("lon")
+                    .map(str::parse)
+                    .expect("No Longitude Data")
+                    .expect("Longitude not parse-able");
+                coordinates.push((id, lat, long));
+            }
+            ("way", false) => {
+                in_way = !element.self_closing;
+                way_nodes.clear();
+                way_tags.clear();
+            }
+            ("nd", false) if in_way => {
+                let id = element
+                    .attribute("ref")
+                    .map(str::parse)
+                    .expect("No node reference found")
+                    .expect("Node reference not parse-able");
+                way_nodes.push(id);
+            }
+            ("tag", false) if in_way => {
+                if let (Some(k), Some(v)) = (element.attribute("k"), element.attribute("v")) {
+                    way_tags.insert(k.to_owned(), v.to_owned());
+                }
+            }
+            ("way", true) => {
+                in_way = false;
+                let nodes = ::std::mem::take(&mut way_nodes);
+                if let Some(way) = OsmWay::from_tags(nodes, &way_tags) {
+                    ways.push(way);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    build_graph_data(&coordinates, &ways)
+}
+
+/// Splits the ways at intersections so that only way endpoints and nodes shared
+/// by several ways become graph nodes. Edge lengths are the summed haversine
+/// distances of the way segments in between.
+fn build_graph_data(
+    coordinates: &[(OsmNodeId, Latitude, Longitude)],
+    ways: &[OsmWay],
+) -> (Vec<NodeInfo>, Vec<EdgeInfo>) {
+    let position: HashMap<OsmNodeId, (Latitude, Longitude)> =
+        coordinates.iter().map(|&(id, lat, long)| (id, (lat, long))).collect();
+
+    let mut usage: HashMap<OsmNodeId, usize> = HashMap::new();
+    for way in ways {
+        for (i, id) in way.nodes.iter().enumerate() {
+            if !position.contains_key(id) {
+                continue;
+            }
+            let is_end = i == 0 || i == way.nodes.len() - 1;
+            *usage.entry(*id).or_insert(0) += if is_end { 2 } else { 1 };
+        }
+    }
+
+    let mut node_ids = HashMap::new();
+    let mut nodes = Vec::new();
+    for &(osm_id, lat, long) in coordinates {
+        if usage.get(&osm_id).is_some_and(|&c| c > 1) && !node_ids.contains_key(&osm_id) {
+            node_ids.insert(osm_id, nodes.len());
+            nodes.push(NodeInfo::new(osm_id, lat, long, 0));
+        }
+    }
+
+    let mut edges = Vec::new();
+    for way in ways {
+        let mut last: Option<(NodeId, (Latitude, Longitude))> = None;
+        let mut length = 0.0;
+        let mut prev_pos: Option<(Latitude, Longitude)> = None;
+        for id in &way.nodes {
+            let pos = match position.get(id) {
+                Some(&pos) => pos,
+                None => continue,
+            };
+            if let Some((lat, long)) = prev_pos {
+                length += haversine(lat, long, pos.0, pos.1);
+            }
+            prev_pos = Some(pos);
+
+            if let Some(&node) = node_ids.get(id) {
+                if let Some((from, _)) = last {
+                    if from != node {
+                        let length = length.round() as Length;
+                        if way.direction != Direction::Backward {
+                            edges.push(EdgeInfo::new(from, node, length, way.road_type, way.speed));
+                        }
+                        if way.direction != Direction::Forward {
+                            edges.push(EdgeInfo::new(node, from, length, way.road_type, way.speed));
+                        }
+                    }
+                }
+                last = Some((node, pos));
+                length = 0.0;
+            }
+        }
+    }
+
+    (nodes, edges)
+}
+
+/// Imports an OpenStreetMap XML extract. Only ways tagged with a highway type
+/// usable by cars are kept. OSM has no elevation data, so every node gets a
+/// height of 0.
+pub fn load_osm_xml<P: AsRef<Path>>(file: P) -> Graph {
+    use std::time::Instant;
+    let start = Instant::now();
+    let mut buffer = String::new();
+    let mut file = File::open(file).expect("File could not be opened");
+    file.read_to_string(&mut buffer).expect(
+        "Could not read file",
+    );
+    let (nodes, edges) = parse_osm_xml(&buffer);
+    let file_loaded = Instant::now();
+    let g = Graph::new(nodes, edges);
+    let graph_created = Instant::now();
+    println!(
+        "file loading time:   {:?}",
+        file_loaded.duration_since(start)
+    );
+    println!(
+        "graph creation time: {:?}",
+        graph_created.duration_since(file_loaded)
+    );
+    g
+}
+
+#[test]
+fn osm_import() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<osm version="0.6">
+  <node id="10" lat="48.0" lon="9.0"/>
+  <node id="11" lat="48.001" lon="9.0"/>
+  <node id="12" lat="48.002" lon="9.0"/>
+  <node id="13" lat="48.001" lon="9.001"/>
+  <node id="14" lat="48.0" lon="9.1"/>
+  <way id="1">
+    <nd ref="10"/>
+    <nd ref="11"/>
+    <nd ref="12"/>
+    <tag k="highway" v="residential"/>
+  </way>
+  <way id="2">
+    <nd ref="11"/>
+    <nd ref="13"/>
+    <tag k="highway" v="primary"/>
+    <tag k="oneway" v="yes"/>
+  </way>
+  <way id="3">
+    <nd ref="10"/>
+    <nd ref="14"/>
+    <tag k="highway" v="footway"/>
+  </way>
+</osm>"#;
+    let (nodes, edges) = parse_osm_xml(xml);
+    let osm_ids: Vec<OsmNodeId> = nodes.iter().map(|n| n.osm_id).collect();
+    assert_eq!(osm_ids, vec![10, 11, 12, 13]);
+
+    assert_eq!(edges.len(), 5);
+    assert!(edges.contains(&EdgeInfo::new(0, 1, 111, RESIDENTIAL, 30)));
+    assert!(edges.contains(&EdgeInfo::new(1, 0, 111, RESIDENTIAL, 30)));
+    assert!(edges.contains(&EdgeInfo::new(1, 3, 74, PRIMARY, 100)));
+    assert!(!edges.iter().any(|e| e.source == 3));
+
+    assert_eq!(decode_entities("A &amp; B &quot;x&quot;"), "A & B \"x\"");
+    assert_eq!(decode_entities("Stra&#223;e &#x26; &lt;&gt;"), "Straße & <>");
+    assert_eq!(decode_entities("R&B &unknown; &"), "R&B &unknown; &");
+    let tag = Element::parse("tag k=\"highway\" v=\"primary&#95;link\"/");
+    assert_eq!(tag.attribute("v"), Some("primary_link"));
+}
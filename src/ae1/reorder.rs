@@ -0,0 +1,275 @@
+use ae1::*;
+
+/// Strategies for renumbering nodes so that nodes close to each other in the
+/// graph are also close to each other in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeOrder {
+    /// Breadth first search order, ignoring edge directions.
+    Bfs,
+    /// Depth first search order, ignoring edge directions.
+    Dfs,
+    /// Order along a Hilbert curve over the coordinates.
+    Hilbert,
+}
+
+/// Mapping between the ids of a reordered graph and the original ids.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeMapping {
+    to_original: Vec<NodeId>,
+    to_new: Vec<NodeId>,
+}
+
+impl NodeMapping {
+    /// Creates the mapping from the original ids in their new order.
+    pub fn from_order(order: Vec<NodeId>) -> NodeMapping {
+        let mut to_new = vec![usize::MAX; order.len()];
+        for (new, &original) in order.iter().enumerate() {
+            assert_eq!(to_new[original], usize::MAX, "node {} ordered twice", original);
+            to_new[original] = new;
+        }
+        NodeMapping {
+            to_original: order,
+            to_new,
+        }
+    }
+
+    pub fn original(&self, id: NodeId) -> NodeId {
+        self.to_original[id]
+    }
+
+    pub fn new_id(&self, original: NodeId) -> NodeId {
+        self.to_new[original]
+    }
+}
+
+/// Side length of the grid coordinates are snapped to for the Hilbert curve.
+const HILBERT_SIZE: u64 = 1 << 16;
+
+/// Position of the grid cell (x, y) along a Hilbert curve filling a
+/// `n` x `n` grid, `n` being a power of two.
+fn hilbert_index(n: u64, mut x: u64, mut y: u64) -> u64 {
+    let mut d = 0;
+    let mut s = n / 2;
+    while s > 0 {
+        let rx = (x & s > 0) as u64;
+        let ry = (y & s > 0) as u64;
+        d += s * s * ((3 * rx) ^ ry);
+        if ry == 0 {
+            if rx == 1 {
+                x = n - 1 - x;
+                y = n - 1 - y;
+            }
+            ::std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    d
+}
+
+impl Graph {
+    /// Renumbers the nodes in the given order.
+    pub fn reordered(&self, order: NodeOrder) -> (Graph, NodeMapping) {
+        let order = match order {
+            NodeOrder::Bfs => self.bfs_order(),
+            NodeOrder::Dfs => self.dfs_order(),
+            NodeOrder::Hilbert => self.hilbert_order(),
+        };
+        let mapping = NodeMapping::from_order(order);
+        (self.permuted(&mapping), mapping)
+    }
+
+    /// The graph with node `id` renamed to `mapping.new_id(id)`.
+    pub fn permuted(&self, mapping: &NodeMapping) -> Graph {
+        let nodes = mapping
+            .to_original
+            .iter()
+            .map(|&original| self.node_info[original].clone())
+            .collect();
+        let mut edges = Vec::with_capacity(self.out_edges.len());
+        for source in 0..self.node_count() {
+            let speeds = self.outgoing_speeds_for(source);
+            for (edge, &speed) in self.outgoing_edges_for(source).iter().zip(speeds) {
+                edges.push(EdgeInfo::new(
+                    mapping.new_id(source),
+                    mapping.new_id(edge.endpoint),
+                    edge.weight,
+                    edge.road_type,
+                    speed,
+                ));
+            }
+        }
+        Graph::new(nodes, edges)
+    }
+
+    /// Neighbors of `node` in both directions.
+    fn neighbors(&self, node: NodeId) -> Vec<NodeId> {
+        self.outgoing_edges_for(node)
+            .iter()
+            .chain(self.ingoing_edges_for(node))
+            .map(|e| e.endpoint)
+            .collect()
+    }
+
+    /// Visits every component with a BFS from its smallest node.
+    fn bfs_order(&self) -> Vec<NodeId> {
+        let mut seen = vec![false; self.node_count()];
+        let mut order = Vec::with_capacity(self.node_count());
+        for root in 0..self.node_count() {
+            if seen[root] {
+                continue;
+            }
+            seen[root] = true;
+            let mut next = order.len();
+            order.push(root);
+            while next < order.len() {
+                for neighbor in self.neighbors(order[next]) {
+                    if !seen[neighbor] {
+                        seen[neighbor] = true;
+                        order.push(neighbor);
+                    }
+                }
+                next += 1;
+            }
+        }
+        order
+    }
+
+    /// Visits every component with a DFS from its smallest node.
+    fn dfs_order(&self) -> Vec<NodeId> {
+        let mut seen = vec![false; self.node_count()];
+        let mut order = Vec::with_capacity(self.node_count());
+        let mut stack = Vec::new();
+        for root in 0..self.node_count() {
+            stack.push(root);
+            while let Some(node) = stack.pop() {
+                if seen[node] {
+                    continue;
+                }
+                seen[node] = true;
+                order.push(node);
+                stack.extend(self.neighbors(node).into_iter().rev().filter(|&n| !seen[n]));
+            }
+        }
+        order
+    }
+
+    /// Nodes along a Hilbert curve over the bounding box of the valid
+    /// coordinates. Invalid coordinates are clamped onto the border.
+    fn hilbert_order(&self) -> Vec<NodeId> {
+        let bbox = self.node_info
+            .iter()
+            .filter(|n| n.lat.abs() <= 90.0 && n.long.abs() <= 180.0)
+            .fold(None, |bbox: Option<BoundingBox>, n| {
+                Some(match bbox {
+                    Some(b) => {
+                        BoundingBox::new(
+                            b.min_lat.min(n.lat),
+                            b.max_lat.max(n.lat),
+                            b.min_long.min(n.long),
+                            b.max_long.max(n.long),
+                        )
+                    }
+                    None => BoundingBox::new(n.lat, n.lat, n.long, n.long),
+                })
+            });
+        let bbox = match bbox {
+            Some(bbox) => bbox,
+            None => return (0..self.node_count()).collect(),
+        };
+        let max_cell = (HILBERT_SIZE - 1) as f64;
+        // NaN fails both comparisons of `max` and `min`, so it ends up as 0
+        let scale = |value: f64, min: f64, max: f64| if max > min {
+            ((value - min) / (max - min) * max_cell).max(0.0).min(max_cell) as u64
+        } else {
+            0
+        };
+        let mut keys: Vec<(u64, NodeId)> = self.node_info
+            .iter()
+            .enumerate()
+            .map(|(id, node)| {
+                let x = scale(node.long, bbox.min_long, bbox.max_long);
+                let y = scale(node.lat, bbox.min_lat, bbox.max_lat);
+                (hilbert_index(HILBERT_SIZE, x, y), id)
+            })
+            .collect();
+        keys.sort();
+        keys.into_iter().map(|(_, id)| id).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ae1::*;
+    use super::hilbert_index;
+
+    #[test]
+    fn hilbert_curve() {
+        let order: Vec<u64> = [(0, 0), (0, 1), (1, 1), (1, 0)]
+            .iter()
+            .map(|&(x, y)| hilbert_index(2, x, y))
+            .collect();
+        assert_eq!(order, vec![0, 1, 2, 3]);
+        assert_eq!(hilbert_index(4, 3, 0), 15);
+
+        let g = Graph::new(
+            vec![
+                NodeInfo::new(0, 48.0, 9.0, 0),
+                NodeInfo::new(1, 48.0, 9.5, 0),
+                NodeInfo::new(2, 48.5, 9.0, 0),
+                NodeInfo::new(3, 48.5, 9.5, 0),
+            ],
+            vec![EdgeInfo::new(0, 3, 5, RESIDENTIAL, 30)],
+        );
+        let (h, mapping) = g.reordered(NodeOrder::Hilbert);
+        assert_eq!(mapping.to_original, vec![0, 2, 3, 1]);
+        assert_eq!(h.outgoing_edges_for(0)[0].endpoint, 2);
+        assert_eq!(h.node_info(3).osm_id, 1);
+
+        let invalid = Graph::new(
+            vec![
+                NodeInfo::new(0, 48.0, 9.0, 0),
+                NodeInfo::new(1, 48.5, 200.0, 0),
+                NodeInfo::new(2, f64::NAN, 9.5, 0),
+                NodeInfo::new(3, 48.5, 9.5, 0),
+            ],
+            Vec::new(),
+        );
+        assert_eq!(invalid.reordered(NodeOrder::Hilbert).1.to_original.len(), 4);
+    }
+
+    #[test]
+    fn reordering_keeps_distances() {
+        // a path 4 - 0 - 3 - 1 - 2 with a detour 4 - 2
+        let mut edges = Vec::new();
+        for &(a, b, length) in &[(4, 0, 1), (0, 3, 2), (3, 1, 3), (1, 2, 4), (4, 2, 20)] {
+            edges.push(EdgeInfo::new(a, b, length, RESIDENTIAL, 30));
+            edges.push(EdgeInfo::new(b, a, length, RESIDENTIAL, 30));
+        }
+        let g = Graph::new(
+            (0..6).map(|i| NodeInfo::new(i, 48.0, 9.0, 0)).collect(),
+            edges,
+        );
+
+        let (bfs, mapping) = g.reordered(NodeOrder::Bfs);
+        assert_eq!(mapping.to_original, vec![0, 3, 4, 1, 2, 5]);
+        let (dfs, dfs_mapping) = g.reordered(NodeOrder::Dfs);
+        assert_eq!(dfs_mapping.to_original, vec![0, 3, 1, 2, 4, 5]);
+
+        for (h, mapping) in &[(bfs, mapping), (dfs, dfs_mapping)] {
+            let mut original = g.dijkstra();
+            let mut reordered = h.dijkstra();
+            for s in 0..g.node_count() {
+                for t in 0..g.node_count() {
+                    let expected = original.distance(s, t).map(|(d, _)| d);
+                    let found = reordered
+                        .distance(mapping.new_id(s), mapping.new_id(t))
+                        .map(|(d, path)| {
+                            assert_eq!(mapping.original(path[0]), s);
+                            d
+                        });
+                    assert_eq!(found, expected);
+                }
+            }
+        }
+    }
+}
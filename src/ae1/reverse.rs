@@ -0,0 +1,234 @@
+use ae1::*;
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// Direction of a search. Forward searches follow outgoing edges away from
+/// the root, backward searches follow ingoing edges and find everything that
+/// leads to the root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+/// Distances from (or, for backward trees, to) the root to every node, with
+/// the next node towards the root.
+#[derive(Debug)]
+pub struct ShortestPathTree {
+    pub root: NodeId,
+    pub direction: Direction,
+    dist: Vec<Length>,
+    parent: Vec<Option<NodeId>>,
+}
+
+impl ShortestPathTree {
+    pub fn distance(&self, node: NodeId) -> Option<Length> {
+        if self.dist[node] == usize::MAX {
+            None
+        } else {
+            Some(self.dist[node])
+        }
+    }
+
+    /// The shortest path between the root and `node` in driving order, so it
+    /// ends at the root for backward trees.
+    pub fn path(&self, node: NodeId) -> Option<Vec<NodeId>> {
+        self.distance(node)?;
+        let mut path = vec![node];
+        let mut cur = node;
+        while let Some(next) = self.parent[cur] {
+            path.push(next);
+            cur = next;
+        }
+        if self.direction == Direction::Forward {
+            path.reverse();
+        }
+        Some(path)
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+struct NodeCost {
+    node: NodeId,
+    cost: usize,
+}
+
+impl Ord for NodeCost {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.cmp(&self.cost)
+    }
+}
+
+impl PartialOrd for NodeCost {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Graph {
+    pub fn edges_in_direction(&self, id: NodeId, direction: Direction) -> &[HalfEdge] {
+        match direction {
+            Direction::Forward => self.outgoing_edges_for(id),
+            Direction::Backward => self.ingoing_edges_for(id),
+        }
+    }
+
+    /// The same graph with every edge pointing the other way.
+    pub fn reversed(&self) -> Graph {
+        let mut edges = Vec::with_capacity(self.out_edges.len());
+        for source in 0..self.node_count() {
+            let speeds = self.outgoing_speeds_for(source);
+            for (edge, &speed) in self.outgoing_edges_for(source).iter().zip(speeds) {
+                edges.push(EdgeInfo::new(
+                    edge.endpoint,
+                    source,
+                    edge.weight,
+                    edge.road_type,
+                    speed,
+                ));
+            }
+        }
+        Graph::new(self.node_info.clone(), edges)
+    }
+
+    /// Marks every node that can be reached from `root` (forward) or that can
+    /// reach `root` (backward).
+    pub fn reachable(&self, root: NodeId, direction: Direction) -> Vec<bool> {
+        let mut seen = vec![false; self.node_count()];
+        let mut stack = vec![root];
+        seen[root] = true;
+        while let Some(node) = stack.pop() {
+            for edge in self.edges_in_direction(node, direction) {
+                if !seen[edge.endpoint] {
+                    seen[edge.endpoint] = true;
+                    stack.push(edge.endpoint);
+                }
+            }
+        }
+        seen
+    }
+
+    /// All nodes from which `target` can be reached.
+    pub fn reaching(&self, target: NodeId) -> Vec<NodeId> {
+        marked(&self.reachable(target, Direction::Backward))
+    }
+
+    /// The strongly connected component of `node`: all nodes that can be
+    /// reached from it and can reach it.
+    pub fn strong_component_of(&self, node: NodeId) -> Vec<NodeId> {
+        let forward = self.reachable(node, Direction::Forward);
+        let backward = self.reachable(node, Direction::Backward);
+        (0..self.node_count())
+            .filter(|&id| forward[id] && backward[id])
+            .collect()
+    }
+
+    pub fn shortest_path_tree(&self, root: NodeId, direction: Direction) -> ShortestPathTree {
+        let mut dist = vec![usize::MAX; self.node_count()];
+        let mut parent = vec![None; self.node_count()];
+        let mut heap = BinaryHeap::new();
+        dist[root] = 0;
+        heap.push(NodeCost {
+            node: root,
+            cost: 0,
+        });
+
+        while let Some(NodeCost { node, cost }) = heap.pop() {
+            if cost > dist[node] {
+                continue;
+            }
+            for edge in self.edges_in_direction(node, direction) {
+                let next = NodeCost {
+                    node: edge.endpoint,
+                    cost: cost + edge.weight,
+                };
+                if next.cost < dist[next.node] {
+                    dist[next.node] = next.cost;
+                    parent[next.node] = Some(node);
+                    heap.push(next);
+                }
+            }
+        }
+
+        ShortestPathTree {
+            root,
+            direction,
+            dist,
+            parent,
+        }
+    }
+}
+
+fn marked(flags: &[bool]) -> Vec<NodeId> {
+    flags
+        .iter()
+        .enumerate()
+        .filter(|&(_, &flag)| flag)
+        .map(|(id, _)| id)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use ae1::*;
+    use super::Direction;
+
+    fn graph() -> Graph {
+        // 0 -> 1 -> 2 -> 0 form a cycle, 3 only leads into it, 4 is only
+        // reached from it and 5 is isolated
+        let edges = vec![
+            EdgeInfo::new(0, 1, 4, RESIDENTIAL, 30),
+            EdgeInfo::new(1, 2, 3, RESIDENTIAL, 30),
+            EdgeInfo::new(2, 0, 2, RESIDENTIAL, 30),
+            EdgeInfo::new(0, 2, 9, RESIDENTIAL, 30),
+            EdgeInfo::new(3, 1, 1, RESIDENTIAL, 30),
+            EdgeInfo::new(3, 0, 8, RESIDENTIAL, 30),
+            EdgeInfo::new(2, 4, 5, RESIDENTIAL, 30),
+        ];
+        Graph::new(
+            (0..6).map(|i| NodeInfo::new(i, 48.0, 9.0, 0)).collect(),
+            edges,
+        )
+    }
+
+    #[test]
+    fn ingoing_edges() {
+        let g = graph();
+        let sources: Vec<NodeId> = g.ingoing_edges_for(0).iter().map(|e| e.endpoint).collect();
+        assert_eq!(sources, vec![2, 3]);
+        assert_eq!(g.ingoing_edges_for(1)[1].weight, 1);
+        assert!(g.ingoing_edges_for(5).is_empty());
+    }
+
+    #[test]
+    fn backward_matches_reversed_forward() {
+        let g = graph();
+        let r = g.reversed();
+        for node in 0..g.node_count() {
+            assert_eq!(
+                g.reachable(node, Direction::Backward),
+                r.reachable(node, Direction::Forward)
+            );
+            let backward = g.shortest_path_tree(node, Direction::Backward);
+            let forward = r.shortest_path_tree(node, Direction::Forward);
+            for other in 0..g.node_count() {
+                assert_eq!(backward.distance(other), forward.distance(other));
+                let mut path = forward.path(other);
+                if let Some(ref mut path) = path {
+                    path.reverse();
+                }
+                assert_eq!(backward.path(other), path);
+            }
+        }
+
+        assert_eq!(g.reaching(4), vec![0, 1, 2, 3, 4]);
+        assert_eq!(g.reaching(3), vec![3]);
+        assert_eq!(g.strong_component_of(1), vec![0, 1, 2]);
+        assert_eq!(g.strong_component_of(5), vec![5]);
+        let tree = g.shortest_path_tree(4, Direction::Backward);
+        assert_eq!(tree.distance(3), Some(9));
+        assert_eq!(tree.path(3), Some(vec![3, 1, 2, 4]));
+        assert_eq!(tree.distance(5), None);
+    }
+}
@@ -0,0 +1,76 @@
+use ae1::*;
+
+impl Graph {
+    /// Returns the graph induced by all nodes for which `keep` returns true.
+    /// Node ids are remapped densely in their original order; the second
+    /// element maps every old id to its new id, if the node was kept.
+    pub fn subgraph<F: Fn(NodeId) -> bool>(&self, keep: F) -> (Graph, Vec<Option<NodeId>>) {
+        let mut mapping = vec![None; self.node_info.len()];
+        let mut nodes = Vec::new();
+        for (id, node) in self.node_info.iter().enumerate() {
+            if keep(id) {
+                mapping[id] = Some(nodes.len());
+                nodes.push(node.clone());
+            }
+        }
+
+        let mut edges = Vec::new();
+        for (source, new_source) in mapping.iter().enumerate() {
+            let new_source = match *new_source {
+                Some(id) => id,
+                None => continue,
+            };
+            let speeds = self.outgoing_speeds_for(source);
+            for (edge, &speed) in self.outgoing_edges_for(source).iter().zip(speeds) {
+                if let Some(new_dest) = mapping[edge.endpoint] {
+                    edges.push(EdgeInfo::new(
+                        new_source,
+                        new_dest,
+                        edge.weight,
+                        edge.road_type,
+                        speed,
+                    ));
+                }
+            }
+        }
+
+        (Graph::new(nodes, edges), mapping)
+    }
+
+    /// Returns the subgraph of all nodes lying inside `bbox`.
+    pub fn crop(&self, bbox: &BoundingBox) -> (Graph, Vec<Option<NodeId>>) {
+        self.subgraph(|id| {
+            let node = &self.node_info[id];
+            bbox.contains(node.lat, node.long)
+        })
+    }
+}
+
+#[test]
+fn crop_graph() {
+    let g = Graph::new(
+        vec![
+            NodeInfo::new(10, 48.0, 9.0, 0),
+            NodeInfo::new(11, 49.0, 9.0, 0),
+            NodeInfo::new(12, 48.5, 9.5, 0),
+            NodeInfo::new(13, 48.2, 9.1, 0),
+        ],
+        vec![
+            EdgeInfo::new(0, 1, 5, 3, 50),
+            EdgeInfo::new(0, 2, 7, 3, 50),
+            EdgeInfo::new(2, 3, 4, 12, 30),
+            EdgeInfo::new(3, 0, 3, 12, 30),
+            EdgeInfo::new(1, 3, 9, 12, 30),
+        ],
+    );
+    let (sub, mapping) = g.crop(&BoundingBox::new(47.5, 48.6, 8.5, 9.6));
+    assert_eq!(mapping, vec![Some(0), None, Some(1), Some(2)]);
+    assert_eq!(sub.node_info.len(), 3);
+    assert_eq!(sub.node_info[2].osm_id, 13);
+    assert_eq!(sub.out_edges.len(), 3);
+    assert_eq!(sub.outgoing_edges_for(0).len(), 1);
+    assert_eq!(sub.outgoing_edges_for(0)[0].endpoint, 1);
+    assert_eq!(sub.outgoing_edges_for(1)[0].endpoint, 2);
+    assert_eq!(sub.outgoing_edges_for(2)[0].endpoint, 0);
+    assert_eq!(sub.outgoing_edges_for(2)[0].road_type, 12);
+}
@@ -0,0 +1,311 @@
+use ae1::*;
+
+use std::path::Path;
+use std::fs::File;
+use std::io::Read;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+
+/// Restriction for turning at `via` when coming from `from` and heading to
+/// `to`. An `Only` restriction forbids every other turn at `via` for cars
+/// coming from `from`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TurnRestriction {
+    No { from: NodeId, via: NodeId, to: NodeId },
+    Only { from: NodeId, via: NodeId, to: NodeId },
+}
+
+fn parse_restrictions(buffer: &str) -> Vec<TurnRestriction> {
+    buffer
+        .lines()
+        .filter(|l| !l.starts_with('#') && !l.trim().is_empty())
+        .map(|l| {
+            let mut raw_data = l.split_whitespace();
+            let kind = raw_data.next().expect("No restriction kind found");
+            let mut ids = raw_data.map(|id| id.parse().expect("Node id not parse-able"));
+            let from = ids.next().expect("No from node found");
+            let via = ids.next().expect("No via node found");
+            let to = ids.next().expect("No to node found");
+            match kind {
+                "no" => TurnRestriction::No { from, via, to },
+                "only" => TurnRestriction::Only { from, via, to },
+                _ => panic!("Unknown restriction kind {}", kind),
+            }
+        })
+        .collect()
+}
+
+/// Loads turn restrictions from a text file with one restriction per line:
+/// `no <from> <via> <to>` or `only <from> <via> <to>` using graph node ids.
+pub fn load_turn_restrictions<P: AsRef<Path>>(file: P) -> Vec<TurnRestriction> {
+    let mut buffer = String::new();
+    let mut file = File::open(file).expect("File could not be opened");
+    file.read_to_string(&mut buffer).expect(
+        "Could not read file",
+    );
+    parse_restrictions(&buffer)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Turn {
+    edge: EdgeId,
+    weight: Length,
+}
+
+/// Turn expanded graph: every edge of the original graph is a node here and
+/// every allowed turn between two edges is an arc weighted with the length of
+/// the second edge plus the turn cost.
+pub struct EdgeGraph<'a> {
+    graph: &'a Graph,
+    edge_source: Vec<NodeId>,
+    turn_offsets: Vec<usize>,
+    turns: Vec<Turn>,
+}
+
+impl<'a> EdgeGraph<'a> {
+    /// Builds the turn expanded graph. U-turns cost `u_turn_cost` or are
+    /// forbidden if it is `None`.
+    pub fn new(
+        graph: &'a Graph,
+        restrictions: &[TurnRestriction],
+        u_turn_cost: Option<Length>,
+    ) -> EdgeGraph<'a> {
+        let mut forbidden = HashSet::new();
+        let mut only = HashMap::new();
+        for restriction in restrictions {
+            match *restriction {
+                TurnRestriction::No { from, via, to } => {
+                    forbidden.insert((from, via, to));
+                }
+                TurnRestriction::Only { from, via, to } => {
+                    only.insert((from, via), to);
+                }
+            }
+        }
+
+        let node_count = graph.node_info.len();
+        let mut edge_source = Vec::with_capacity(graph.out_edges.len());
+        for node in 0..node_count {
+            for _ in graph.outgoing_edges_for(node) {
+                edge_source.push(node);
+            }
+        }
+
+        let mut turn_offsets = Vec::with_capacity(graph.out_edges.len() + 1);
+        let mut turns = Vec::new();
+        for (edge, &from) in graph.out_edges.iter().zip(edge_source.iter()) {
+            turn_offsets.push(turns.len());
+            let via = edge.endpoint;
+            let first = graph.node_offsets[via].out_start;
+            for (i, next) in graph.outgoing_edges_for(via).iter().enumerate() {
+                let to = next.endpoint;
+                if forbidden.contains(&(from, via, to)) {
+                    continue;
+                }
+                if only.get(&(from, via)).is_some_and(|&only_to| only_to != to) {
+                    continue;
+                }
+                let turn_cost = if to == from {
+                    match u_turn_cost {
+                        Some(cost) => cost,
+                        None => continue,
+                    }
+                } else {
+                    0
+                };
+                turns.push(Turn {
+                    edge: first + i,
+                    weight: next.weight + turn_cost,
+                });
+            }
+        }
+        turn_offsets.push(turns.len());
+
+        EdgeGraph {
+            graph,
+            edge_source,
+            turn_offsets,
+            turns,
+        }
+    }
+
+    fn turns_for(&self, edge: EdgeId) -> &[Turn] {
+        &self.turns[self.turn_offsets[edge]..self.turn_offsets[edge + 1]]
+    }
+
+    pub fn dijkstra(&self) -> EdgeDijkstra<'_> {
+        EdgeDijkstra {
+            dist: vec![usize::MAX; self.edge_source.len()],
+            prev: vec![usize::MAX; self.edge_source.len()],
+            touched: Default::default(),
+            graph: self,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+struct EdgeCost {
+    edge: EdgeId,
+    cost: usize,
+}
+
+impl Ord for EdgeCost {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.cmp(&self.cost)
+    }
+}
+
+impl PartialOrd for EdgeCost {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub struct EdgeDijkstra<'a> {
+    dist: Vec<Length>,
+    prev: Vec<EdgeId>,
+    touched: Vec<EdgeId>,
+    graph: &'a EdgeGraph<'a>,
+}
+
+impl<'a> EdgeDijkstra<'a> {
+    /// Shortest path between two nodes of the original graph respecting the
+    /// turn restrictions. The path is given as original node ids.
+    pub fn distance(&mut self, source: NodeId, dest: NodeId) -> Option<(Length, VecDeque<NodeId>)> {
+        for edge in self.touched.drain(..) {
+            self.dist[edge] = usize::MAX;
+            self.prev[edge] = usize::MAX;
+        }
+        if source == dest {
+            return Some((0, vec![source].into_iter().collect()));
+        }
+
+        let base = self.graph.graph;
+        let mut heap = BinaryHeap::new();
+        let first = base.node_offsets[source].out_start;
+        for (i, edge) in base.outgoing_edges_for(source).iter().enumerate() {
+            let id = first + i;
+            if edge.weight < self.dist[id] {
+                self.dist[id] = edge.weight;
+                self.touched.push(id);
+                heap.push(EdgeCost {
+                    edge: id,
+                    cost: edge.weight,
+                });
+            }
+        }
+
+        while let Some(EdgeCost { edge, cost }) = heap.pop() {
+            if cost > self.dist[edge] {
+                continue;
+            }
+            if base.out_edges[edge].endpoint == dest {
+                let mut path = VecDeque::new();
+                path.push_front(dest);
+                let mut cur = edge;
+                while cur != usize::MAX {
+                    path.push_front(self.graph.edge_source[cur]);
+                    cur = self.prev[cur];
+                }
+                return Some((cost, path));
+            }
+
+            for turn in self.graph.turns_for(edge) {
+                let next = EdgeCost {
+                    edge: turn.edge,
+                    cost: cost + turn.weight,
+                };
+                if next.cost < self.dist[next.edge] {
+                    self.prev[next.edge] = edge;
+                    self.dist[next.edge] = next.cost;
+                    self.touched.push(next.edge);
+                    heap.push(next);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[test]
+fn turn_restrictions() {
+    //  0 - 1 - 2
+    //      |   |
+    //      3 - 4
+    let mut edges = Vec::new();
+    for &(a, b, length) in &[(0, 1, 1), (1, 2, 1), (1, 3, 1), (2, 4, 1), (3, 4, 1)] {
+        edges.push(EdgeInfo::new(a, b, length, RESIDENTIAL, 30));
+        edges.push(EdgeInfo::new(b, a, length, RESIDENTIAL, 30));
+    }
+    let g = Graph::new(
+        (0..5).map(|i| NodeInfo::new(i, 48.0, 9.0, 0)).collect(),
+        edges,
+    );
+
+    let plain = EdgeGraph::new(&g, &[], Some(0));
+    let (dist, path) = plain.dijkstra().distance(0, 3).unwrap();
+    assert_eq!(dist, 2);
+    assert_eq!(path, vec![0, 1, 3]);
+
+    let no_right = [TurnRestriction::No {
+        from: 0,
+        via: 1,
+        to: 3,
+    }];
+    let restricted = EdgeGraph::new(&g, &no_right, None);
+    let mut d = restricted.dijkstra();
+    let (dist, path) = d.distance(0, 3).unwrap();
+    assert_eq!(dist, 4);
+    assert_eq!(path, vec![0, 1, 2, 4, 3]);
+
+    let only_straight = parse_restrictions("# only straight on\nonly 0 1 2\n");
+    assert_eq!(
+        only_straight,
+        vec![
+            TurnRestriction::Only {
+                from: 0,
+                via: 1,
+                to: 2,
+            },
+        ]
+    );
+    let restricted = EdgeGraph::new(&g, &only_straight, Some(1));
+    let (dist, path) = restricted.dijkstra().distance(0, 3).unwrap();
+    assert_eq!(dist, 4);
+    assert_eq!(path, vec![0, 1, 2, 4, 3]);
+
+    // starting at 1 the edge to 0 is taken without turning, so no u-turn
+    // cost is added
+    let (dist, path) = restricted.dijkstra().distance(1, 0).unwrap();
+    assert_eq!(dist, 1);
+    assert_eq!(path, vec![1, 0]);
+    let (dist, _) = restricted.dijkstra().distance(0, 0).unwrap();
+    assert_eq!(dist, 0);
+
+    //  0 - 1 - 2
+    //      |
+    //      3
+    // going straight on at 1 is forbidden, so 2 is only reachable by turning
+    // around at the dead end 3
+    let mut edges = Vec::new();
+    for &(a, b) in &[(0, 1), (1, 2), (1, 3)] {
+        edges.push(EdgeInfo::new(a, b, 1, RESIDENTIAL, 30));
+        edges.push(EdgeInfo::new(b, a, 1, RESIDENTIAL, 30));
+    }
+    let g = Graph::new(
+        (0..4).map(|i| NodeInfo::new(i, 48.0, 9.0, 0)).collect(),
+        edges,
+    );
+    let no_straight = [TurnRestriction::No {
+        from: 0,
+        via: 1,
+        to: 2,
+    }];
+    let (dist, path) = EdgeGraph::new(&g, &no_straight, Some(2))
+        .dijkstra()
+        .distance(0, 2)
+        .unwrap();
+    assert_eq!(dist, 6);
+    assert_eq!(path, vec![0, 1, 3, 1, 2]);
+    assert!(EdgeGraph::new(&g, &no_straight, None).dijkstra().distance(0, 2).is_none());
+}
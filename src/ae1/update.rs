@@ -0,0 +1,49 @@
+use ae1::*;
+
+impl Graph {
+    /// Sets the weight of all edges from `source` to `dest` to the given length
+    /// for every `(source, dest, length)` update. Returns how many edges were
+    /// changed.
+    pub fn update_weights(&mut self, updates: &[(NodeId, NodeId, Length)]) -> usize {
+        let mut changed = 0;
+        for &(source, dest, weight) in updates {
+            let out_range = self.node_offsets[source].out_start..
+                self.node_offsets[source + 1].out_start;
+            for edge in &mut self.out_edges[out_range] {
+                if edge.endpoint == dest {
+                    edge.weight = weight;
+                    changed += 1;
+                }
+            }
+            let in_range = self.node_offsets[dest].in_start..self.node_offsets[dest + 1].in_start;
+            for edge in &mut self.in_edges[in_range] {
+                if edge.endpoint == source {
+                    edge.weight = weight;
+                }
+            }
+        }
+        changed
+    }
+}
+
+#[test]
+fn update_weights() {
+    let mut g = Graph::new(
+        vec![
+            NodeInfo::new(1, 48.0, 9.0, 0),
+            NodeInfo::new(2, 48.0, 9.0, 0),
+            NodeInfo::new(3, 48.0, 9.0, 0),
+        ],
+        vec![
+            EdgeInfo::new(0, 1, 1, RESIDENTIAL, 30),
+            EdgeInfo::new(1, 2, 1, RESIDENTIAL, 30),
+            EdgeInfo::new(0, 2, 5, RESIDENTIAL, 30),
+        ],
+    );
+    assert_eq!(g.dijkstra().distance(0, 2).unwrap().0, 2);
+
+    assert_eq!(g.update_weights(&[(1, 2, 10), (2, 0, 3)]), 1);
+    assert_eq!(g.dijkstra().distance(0, 2).unwrap().0, 5);
+    let in_weights: Vec<Length> = g.in_edges.iter().map(|e| e.weight).collect();
+    assert_eq!(in_weights, vec![1, 5, 10]);
+}
@@ -0,0 +1,327 @@
+use ae1::*;
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Summary of a graph and the suspicious things found in it. Degree
+/// histograms are indexed by degree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphStats {
+    pub node_count: usize,
+    pub edge_count: usize,
+    pub out_degrees: Vec<usize>,
+    pub in_degrees: Vec<usize>,
+    pub isolated_nodes: usize,
+    pub self_loops: usize,
+    pub parallel_edges: usize,
+    pub zero_length_edges: usize,
+    pub out_of_range_edges: usize,
+    pub invalid_coordinates: usize,
+    /// Lines of a graph file that don't parse or are missing from it.
+    pub malformed_lines: usize,
+    pub bounding_box: Option<BoundingBox>,
+}
+
+impl GraphStats {
+    /// Collects the statistics from node coordinates as (lat, long) and
+    /// (source, dest, length) triples. Works on raw input, so edges with
+    /// endpoints outside of the node range are counted instead of panicking.
+    pub fn collect<I>(coordinates: &[(Latitude, Longitude)], edges: I) -> GraphStats
+    where
+        I: IntoIterator<Item = (NodeId, NodeId, Length)>,
+    {
+        let node_count = coordinates.len();
+        let mut out_degree = vec![0; node_count];
+        let mut in_degree = vec![0; node_count];
+        let mut pairs = Vec::new();
+        let mut stats = GraphStats {
+            node_count,
+            edge_count: 0,
+            out_degrees: Vec::new(),
+            in_degrees: Vec::new(),
+            isolated_nodes: 0,
+            self_loops: 0,
+            parallel_edges: 0,
+            zero_length_edges: 0,
+            out_of_range_edges: 0,
+            invalid_coordinates: 0,
+            malformed_lines: 0,
+            bounding_box: None,
+        };
+
+        for (source, dest, length) in edges {
+            stats.edge_count += 1;
+            if source >= node_count || dest >= node_count {
+                stats.out_of_range_edges += 1;
+                continue;
+            }
+            out_degree[source] += 1;
+            in_degree[dest] += 1;
+            if source == dest {
+                stats.self_loops += 1;
+            }
+            if length == 0 {
+                stats.zero_length_edges += 1;
+            }
+            pairs.push((source, dest));
+        }
+        pairs.sort();
+        stats.parallel_edges = pairs.windows(2).filter(|w| w[0] == w[1]).count();
+
+        stats.out_degrees = histogram(&out_degree);
+        stats.in_degrees = histogram(&in_degree);
+        stats.isolated_nodes = (0..node_count)
+            .filter(|&id| out_degree[id] == 0 && in_degree[id] == 0)
+            .count();
+
+        for &(lat, long) in coordinates {
+            if !(lat.abs() <= 90.0 && long.abs() <= 180.0) {
+                stats.invalid_coordinates += 1;
+                continue;
+            }
+            let bbox = stats.bounding_box.get_or_insert(
+                BoundingBox::new(lat, lat, long, long),
+            );
+            bbox.min_lat = bbox.min_lat.min(lat);
+            bbox.max_lat = bbox.max_lat.max(lat);
+            bbox.min_long = bbox.min_long.min(long);
+            bbox.max_long = bbox.max_long.max(long);
+        }
+        stats
+    }
+
+    pub fn max_out_degree(&self) -> usize {
+        self.out_degrees.len().saturating_sub(1)
+    }
+
+    pub fn max_in_degree(&self) -> usize {
+        self.in_degrees.len().saturating_sub(1)
+    }
+
+    pub fn average_degree(&self) -> f64 {
+        if self.node_count == 0 {
+            0.0
+        } else {
+            (self.edge_count - self.out_of_range_edges) as f64 / self.node_count as f64
+        }
+    }
+
+    /// Human readable descriptions of everything that looks wrong.
+    pub fn problems(&self) -> Vec<String> {
+        let checks = [
+            (self.malformed_lines, "malformed lines"),
+            (self.out_of_range_edges, "edges with endpoints out of range"),
+            (self.self_loops, "self-loops"),
+            (self.parallel_edges, "parallel edges"),
+            (self.zero_length_edges, "edges of length 0"),
+            (self.isolated_nodes, "isolated nodes"),
+            (self.invalid_coordinates, "nodes with invalid coordinates"),
+        ];
+        checks
+            .iter()
+            .filter(|&&(count, _)| count > 0)
+            .map(|&(count, what)| format!("{} {}", count, what))
+            .collect()
+    }
+}
+
+fn histogram(degrees: &[usize]) -> Vec<usize> {
+    let mut histogram = vec![0; degrees.iter().max().map_or(0, |&d| d + 1)];
+    for &degree in degrees {
+        histogram[degree] += 1;
+    }
+    histogram
+}
+
+impl fmt::Display for GraphStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "nodes:          {}", self.node_count)?;
+        writeln!(f, "edges:          {}", self.edge_count)?;
+        writeln!(
+            f,
+            "degree:         avg {:.2}, max out {}, max in {}",
+            self.average_degree(),
+            self.max_out_degree(),
+            self.max_in_degree()
+        )?;
+        for (degree, count) in self.out_degrees.iter().enumerate().filter(|&(_, &c)| c > 0) {
+            writeln!(f, "  out degree {:>3}: {}", degree, count)?;
+        }
+        if let Some(bbox) = self.bounding_box {
+            writeln!(
+                f,
+                "bounding box:   lat {} - {}, long {} - {}",
+                bbox.min_lat,
+                bbox.max_lat,
+                bbox.min_long,
+                bbox.max_long
+            )?;
+        }
+        for problem in self.problems() {
+            writeln!(f, "problem:        {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl Graph {
+    pub fn stats(&self) -> GraphStats {
+        let coordinates: Vec<(Latitude, Longitude)> =
+            self.node_info.iter().map(|n| (n.lat, n.long)).collect();
+        let edges = (0..self.node_count()).flat_map(|source| {
+            self.outgoing_edges_for(source).iter().map(move |e| {
+                (source, e.endpoint, e.weight)
+            })
+        });
+        GraphStats::collect(&coordinates, edges)
+    }
+
+    /// The statistics of the graph, or the problems found in it.
+    pub fn validate(&self) -> Result<GraphStats, Vec<String>> {
+        let stats = self.stats();
+        let problems = stats.problems();
+        if problems.is_empty() {
+            Ok(stats)
+        } else {
+            Err(problems)
+        }
+    }
+}
+
+/// Statistics of a `.graph` file, collected without building the graph so
+/// broken files are reported instead of crashing the loader. Only failing to
+/// read the file is an error.
+pub fn check_graph_file<P: AsRef<Path>>(file: P) -> io::Result<GraphStats> {
+    let mut buffer = String::new();
+    File::open(file)?.read_to_string(&mut buffer)?;
+    Ok(check_graph(&buffer))
+}
+
+/// Parses the graph like the loader does, but counts the lines that don't
+/// parse, and the ones missing from the counts in the header, as malformed.
+/// A malformed node line keeps its id, so it doesn't shift the edges.
+fn check_graph(buffer: &str) -> GraphStats {
+    fn parse<T: ::std::str::FromStr>(field: Option<&str>) -> Option<T> {
+        field.and_then(|f| f.parse().ok())
+    }
+
+    let mut lines = buffer.lines().skip_while(|l| l.starts_with('#') || l.is_empty());
+    let node_count: Option<usize> = parse(lines.next());
+    let edge_count: Option<usize> = parse(lines.next());
+    let (node_count, edge_count) = match (node_count, edge_count) {
+        (Some(nodes), Some(edges)) => (nodes, edges),
+        _ => {
+            let mut stats = GraphStats::collect(&[], Vec::new());
+            stats.malformed_lines = 1;
+            return stats;
+        }
+    };
+
+    let mut malformed_lines = 0;
+    let mut malformed_nodes = 0;
+    let mut coordinates = Vec::new();
+    for line in lines.by_ref().take(node_count) {
+        let mut fields = line.split(' ').skip(1);
+        let osm_id: Option<OsmNodeId> = parse(fields.next());
+        let lat: Option<Latitude> = parse(fields.next());
+        let long: Option<Longitude> = parse(fields.next());
+        let height: Option<Height> = parse(fields.next());
+        match (osm_id, lat, long, height) {
+            (Some(_), Some(lat), Some(long), Some(_)) => coordinates.push((lat, long)),
+            _ => {
+                malformed_nodes += 1;
+                coordinates.push((f64::NAN, f64::NAN));
+            }
+        }
+    }
+    malformed_lines += malformed_nodes + node_count - coordinates.len();
+
+    let mut edges = Vec::new();
+    let mut edge_lines = 0;
+    for line in lines {
+        edge_lines += 1;
+        let mut fields = line.split(' ');
+        let source: Option<NodeId> = parse(fields.next());
+        let dest: Option<NodeId> = parse(fields.next());
+        let length: Option<Length> = parse(fields.next());
+        let road_type: Option<RoadType> = parse(fields.next());
+        let speed: Option<Speed> = parse(fields.next());
+        match (source, dest, length, road_type, speed) {
+            (Some(source), Some(dest), Some(length), Some(_), Some(_)) => {
+                edges.push((source, dest, length))
+            }
+            _ => malformed_lines += 1,
+        }
+    }
+    malformed_lines += edge_count.saturating_sub(edge_lines);
+
+    let mut stats = GraphStats::collect(&coordinates, edges);
+    // the coordinates of malformed nodes are unknown, not invalid
+    stats.invalid_coordinates -= malformed_nodes;
+    stats.malformed_lines = malformed_lines;
+    stats
+}
+
+#[test]
+fn graph_stats() {
+    let g = Graph::new(
+        vec![
+            NodeInfo::new(1, 48.0, 9.0, 0),
+            NodeInfo::new(2, 48.5, 9.2, 0),
+            NodeInfo::new(3, 48.2, 8.9, 0),
+            NodeInfo::new(4, 48.1, 9.1, 0),
+        ],
+        vec![
+            EdgeInfo::new(0, 1, 10, RESIDENTIAL, 30),
+            EdgeInfo::new(1, 0, 10, RESIDENTIAL, 30),
+            EdgeInfo::new(0, 2, 7, RESIDENTIAL, 30),
+            EdgeInfo::new(3, 2, 4, RESIDENTIAL, 30),
+        ],
+    );
+    let stats = g.validate().expect("graph is fine");
+    assert_eq!(stats.out_degrees, vec![1, 2, 1]);
+    assert_eq!(stats.in_degrees, vec![1, 2, 1]);
+    assert_eq!(stats.max_out_degree(), 2);
+    assert_eq!(stats.average_degree(), 1.0);
+    assert_eq!(stats.bounding_box, Some(BoundingBox::new(48.0, 48.5, 8.9, 9.2)));
+
+    let stats = GraphStats::collect(
+        &[(48.0, 9.0), (48.1, 9.1), (95.0, 9.0), (48.2, 9.2)],
+        vec![(0, 1, 5), (0, 1, 6), (0, 1, 7), (1, 1, 3), (1, 0, 0), (1, 7, 4)],
+    );
+    assert_eq!(stats.edge_count, 6);
+    assert_eq!(stats.parallel_edges, 2);
+    assert_eq!(stats.self_loops, 1);
+    assert_eq!(stats.zero_length_edges, 1);
+    assert_eq!(stats.out_of_range_edges, 1);
+    assert_eq!(stats.isolated_nodes, 2);
+    assert_eq!(stats.invalid_coordinates, 1);
+    assert_eq!(stats.bounding_box, Some(BoundingBox::new(48.0, 48.2, 9.0, 9.2)));
+    assert_eq!(
+        stats.problems(),
+        vec![
+            "1 edges with endpoints out of range",
+            "1 self-loops",
+            "2 parallel edges",
+            "1 edges of length 0",
+            "2 isolated nodes",
+            "1 nodes with invalid coordinates",
+        ]
+    );
+}
+
+#[test]
+fn check_truncated_graph() {
+    let stats = check_graph("3\n3\n0 23 48.7 9.1 12\n1 27 48.8 x 13\n2 53 48.9 9.2 14\n0 1 10 3 30\n1 2 12");
+    assert_eq!(stats.node_count, 3);
+    assert_eq!(stats.edge_count, 1);
+    assert_eq!(stats.invalid_coordinates, 0);
+    assert_eq!(stats.malformed_lines, 3);
+    assert_eq!(stats.problems()[0], "3 malformed lines");
+
+    let stats = check_graph("5\n0 23 48.7");
+    assert_eq!(stats.malformed_lines, 1);
+    assert_eq!(stats.node_count, 0);
+}
@@ -0,0 +1,80 @@
+use ae1::*;
+
+use std::path::Path;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/// Writes the graph in the text format `load_graph` reads.
+pub fn write_graph<P: AsRef<Path>>(graph: &Graph, file: P) -> io::Result<()> {
+    let file = File::create(file)?;
+    let mut writer = BufWriter::new(file);
+    write_graph_to(graph, &mut writer)?;
+    writer.flush()
+}
+
+fn write_graph_to<W: Write>(graph: &Graph, writer: &mut W) -> io::Result<()> {
+    writeln!(writer, "{}", graph.node_info.len())?;
+    writeln!(writer, "{}", graph.out_edges.len())?;
+    for (id, node) in graph.node_info.iter().enumerate() {
+        writeln!(
+            writer,
+            "{} {} {} {} {}",
+            id,
+            node.osm_id,
+            node.lat,
+            node.long,
+            node.height
+        )?;
+    }
+    for source in 0..graph.node_info.len() {
+        let speeds = graph.outgoing_speeds_for(source);
+        for (edge, speed) in graph.outgoing_edges_for(source).iter().zip(speeds) {
+            writeln!(
+                writer,
+                "{} {} {} {} {}",
+                source,
+                edge.endpoint,
+                edge.weight,
+                edge.road_type,
+                speed
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn write_read_round_trip() {
+    use super::load::parse_graph;
+
+    let g = Graph::new(
+        vec![
+            NodeInfo::new(23, 48.7453, 9.1059, 12),
+            NodeInfo::new(27, 48.7461, 9.1063, 13),
+            NodeInfo::new(53, 48.7470, 9.1070, 14),
+        ],
+        vec![
+            EdgeInfo::new(0, 1, 10, 3, 30),
+            EdgeInfo::new(1, 2, 12, 12, 50),
+            EdgeInfo::new(2, 0, 15, 3, 50),
+        ],
+    );
+    let mut buffer = Vec::new();
+    write_graph_to(&g, &mut buffer).unwrap();
+    let text = String::from_utf8(buffer).unwrap();
+    let (nodes, edges) = parse_graph(&text);
+
+    assert_eq!(nodes.len(), 3);
+    assert_eq!(nodes[1].osm_id, 27);
+    assert_eq!(nodes[1].lat, 48.7461);
+    assert_eq!(nodes[1].long, 9.1063);
+    assert_eq!(nodes[1].height, 13);
+    assert_eq!(
+        edges,
+        vec![
+            EdgeInfo::new(0, 1, 10, 3, 30),
+            EdgeInfo::new(1, 2, 12, 12, 50),
+            EdgeInfo::new(2, 0, 15, 3, 50),
+        ]
+    );
+}
@@ -74,7 +74,7 @@ impl<'a> ChDijkstra<'a> {
 
         loop {
             if s_empty && t_empty {
-                return usize::MAX;
+                return min_candidate;
             }
             if s_bigger && t_bigger {
                 return min_candidate;
@@ -97,7 +97,7 @@ impl<'a> ChDijkstra<'a> {
                     if self.graph.level[edge.endpoint] >= self.graph.level[node] {
                         let next = NodeCost {
                             node: edge.endpoint,
-                            cost: cost + edge.weight,
+                            cost: cost.saturating_add(edge.weight),
                         };
 
                         if next.cost < self.s_dist[next.node] {
@@ -135,7 +135,7 @@ impl<'a> ChDijkstra<'a> {
 
                         let next = NodeCost {
                             node: edge.endpoint,
-                            cost: cost + edge.weight,
+                            cost: cost.saturating_add(edge.weight),
                         };
                         if next.cost < self.t_dist[next.node] {
                             self.t_dist[next.node] = next.cost;
@@ -0,0 +1,360 @@
+use ae1::Graph;
+use ae2::{EdgeId, Length, NodeId};
+
+use std::cmp::Ordering;
+
+/// Subgraphs up to this size are not dissected any further.
+const LEAF_SIZE: usize = 32;
+const NO_NODE: NodeId = usize::MAX;
+
+/// Customizable contraction hierarchy. The node order and the shortcuts only
+/// depend on the topology of the graph, edge weights are added afterwards by
+/// `customize` and can be replaced cheaply for every new metric.
+///
+/// Internally nodes are identified by their rank, edges are undirected and
+/// stored at their lower endpoint with one weight for each direction.
+pub struct Cch {
+    rank: Vec<usize>,
+    first_out: Vec<EdgeId>,
+    head: Vec<usize>,
+    parent: Vec<usize>,
+    up_weight: Vec<Length>,
+    down_weight: Vec<Length>,
+    original: Vec<Option<(EdgeId, bool)>>,
+}
+
+impl Cch {
+    /// Computes a nested dissection order for `graph` and contracts it.
+    pub fn new(graph: &Graph) -> Cch {
+        let neighbors = undirected_neighbors(graph);
+        let order = nested_dissection(graph, &neighbors);
+        let mut rank = vec![0; order.len()];
+        for (r, &node) in order.iter().enumerate() {
+            rank[node] = r;
+        }
+
+        let mut up: Vec<Vec<usize>> = vec![Vec::new(); order.len()];
+        for (node, list) in neighbors.iter().enumerate() {
+            for &neighbor in list {
+                if rank[neighbor] > rank[node] {
+                    up[rank[node]].push(rank[neighbor]);
+                }
+            }
+        }
+
+        // elimination game: the neighbors of a contracted node become a
+        // clique, which is the same as merging them into the lowest of them
+        let mut first_out = Vec::with_capacity(order.len() + 1);
+        let mut head = Vec::new();
+        let mut parent = vec![NO_NODE; order.len()];
+        for v in 0..order.len() {
+            let mut list = ::std::mem::take(&mut up[v]);
+            list.sort_unstable();
+            list.dedup();
+            if let Some((&p, rest)) = list.split_first() {
+                parent[v] = p;
+                up[p].extend_from_slice(rest);
+            }
+            first_out.push(head.len());
+            head.extend(list);
+        }
+        first_out.push(head.len());
+
+        let mut cch = Cch {
+            rank,
+            first_out,
+            up_weight: vec![usize::MAX; head.len()],
+            down_weight: vec![usize::MAX; head.len()],
+            head,
+            parent,
+            original: Vec::new(),
+        };
+
+        let mut original = Vec::new();
+        for source in 0..graph.node_count() {
+            for edge in graph.outgoing_edges_for(source) {
+                let (a, b) = (cch.rank[source], cch.rank[edge.endpoint()]);
+                original.push(match a.cmp(&b) {
+                    Ordering::Less => Some((cch.edge_between(a, b), true)),
+                    Ordering::Greater => Some((cch.edge_between(b, a), false)),
+                    Ordering::Equal => None,
+                });
+            }
+        }
+        cch.original = original;
+        cch
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.head.len()
+    }
+
+    /// Applies a metric given as weight per edge of the original graph in the
+    /// order of `Graph::edge_weights`.
+    pub fn customize(&mut self, weights: &[Length]) {
+        assert_eq!(weights.len(), self.original.len(), "one weight per edge needed");
+        for w in self.up_weight.iter_mut().chain(self.down_weight.iter_mut()) {
+            *w = usize::MAX;
+        }
+        for (&weight, original) in weights.iter().zip(self.original.iter()) {
+            match *original {
+                Some((edge, true)) => {
+                    self.up_weight[edge] = self.up_weight[edge].min(weight);
+                }
+                Some((edge, false)) => {
+                    self.down_weight[edge] = self.down_weight[edge].min(weight);
+                }
+                None => {}
+            }
+        }
+
+        // lower triangles u - v - w with v below u and w, in order of v
+        for v in 0..self.rank.len() {
+            let edges = self.first_out[v]..self.first_out[v + 1];
+            for first in edges.clone() {
+                for second in first + 1..edges.end {
+                    let upper = self.edge_between(self.head[first], self.head[second]);
+                    let up = self.down_weight[first].saturating_add(self.up_weight[second]);
+                    let down = self.down_weight[second].saturating_add(self.up_weight[first]);
+                    if up < self.up_weight[upper] {
+                        self.up_weight[upper] = up;
+                    }
+                    if down < self.down_weight[upper] {
+                        self.down_weight[upper] = down;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn query(&self) -> CchQuery<'_> {
+        CchQuery {
+            forward: vec![usize::MAX; self.rank.len()],
+            backward: vec![usize::MAX; self.rank.len()],
+            cch: self,
+        }
+    }
+
+    /// Relaxes the upward edges of `start` and all its elimination tree
+    /// ancestors, which are exactly the nodes an upward search can reach.
+    fn relax_ancestors(&self, start: usize, dist: &mut [Length], weights: &[Length]) {
+        dist[start] = 0;
+        let mut v = start;
+        while v != NO_NODE {
+            if dist[v] != usize::MAX {
+                for edge in self.first_out[v]..self.first_out[v + 1] {
+                    let next = dist[v].saturating_add(weights[edge]);
+                    if next < dist[self.head[edge]] {
+                        dist[self.head[edge]] = next;
+                    }
+                }
+            }
+            v = self.parent[v];
+        }
+    }
+
+    fn reset_ancestors(&self, start: usize, dist: &mut [Length]) {
+        let mut v = start;
+        while v != NO_NODE {
+            dist[v] = usize::MAX;
+            v = self.parent[v];
+        }
+    }
+
+    /// Edge from the lower ranked `low` to `high`, which must exist.
+    fn edge_between(&self, low: usize, high: usize) -> EdgeId {
+        let start = self.first_out[low];
+        let end = self.first_out[low + 1];
+        start +
+            self.head[start..end].binary_search(&high).expect(
+                "CCH is not chordal",
+            )
+    }
+}
+
+/// Neighbors of every node ignoring edge directions, without duplicates and
+/// self loops.
+fn undirected_neighbors(graph: &Graph) -> Vec<Vec<NodeId>> {
+    let mut neighbors = vec![Vec::new(); graph.node_count()];
+    for source in 0..graph.node_count() {
+        for edge in graph.outgoing_edges_for(source) {
+            if edge.endpoint() != source {
+                neighbors[source].push(edge.endpoint());
+                neighbors[edge.endpoint()].push(source);
+            }
+        }
+    }
+    for list in &mut neighbors {
+        list.sort_unstable();
+        list.dedup();
+    }
+    neighbors
+}
+
+/// Contraction order from recursive bisection along the longer side of the
+/// coordinate bounding box. The separator of each cut is contracted after
+/// both halves.
+fn nested_dissection(graph: &Graph, neighbors: &[Vec<NodeId>]) -> Vec<NodeId> {
+    let mut side = vec![0u8; graph.node_count()];
+    let mut order = Vec::with_capacity(graph.node_count());
+    dissect(graph, neighbors, (0..graph.node_count()).collect(), &mut side, &mut order);
+    order
+}
+
+fn dissect(
+    graph: &Graph,
+    neighbors: &[Vec<NodeId>],
+    mut nodes: Vec<NodeId>,
+    side: &mut [u8],
+    order: &mut Vec<NodeId>,
+) {
+    if nodes.len() <= LEAF_SIZE {
+        order.extend(nodes);
+        return;
+    }
+
+    let lat = |n: &NodeId| graph.node_info(*n).lat();
+    let long = |n: &NodeId| graph.node_info(*n).long();
+    let extent = |f: &dyn Fn(&NodeId) -> f64| {
+        let min = nodes.iter().map(f).fold(f64::INFINITY, f64::min);
+        let max = nodes.iter().map(f).fold(f64::NEG_INFINITY, f64::max);
+        max - min
+    };
+    if extent(&lat) > extent(&long) {
+        nodes.sort_by(|a, b| lat(a).partial_cmp(&lat(b)).unwrap_or(Ordering::Equal));
+    } else {
+        nodes.sort_by(|a, b| long(a).partial_cmp(&long(b)).unwrap_or(Ordering::Equal));
+    }
+
+    let right = nodes.split_off(nodes.len() / 2);
+    let left = nodes;
+    for &n in &left {
+        side[n] = 1;
+    }
+    for &n in &right {
+        side[n] = 2;
+    }
+
+    let boundary = |part: &[NodeId], other: u8, side: &[u8]| -> Vec<bool> {
+        part.iter()
+            .map(|&n| neighbors[n].iter().any(|&m| side[m] == other))
+            .collect()
+    };
+    let left_boundary = boundary(&left, 2, side);
+    let right_boundary = boundary(&right, 1, side);
+    let cut_left = left_boundary.iter().filter(|&&b| b).count() <=
+        right_boundary.iter().filter(|&&b| b).count();
+
+    let mut separator = Vec::new();
+    let mut split = |part: Vec<NodeId>, in_separator: Vec<bool>, cut: bool| {
+        let mut rest = Vec::with_capacity(part.len());
+        for (n, is_boundary) in part.into_iter().zip(in_separator) {
+            if cut && is_boundary {
+                separator.push(n);
+            } else {
+                rest.push(n);
+            }
+        }
+        rest
+    };
+    let left = split(left, left_boundary, cut_left);
+    let right = split(right, right_boundary, !cut_left);
+    for &n in &separator {
+        side[n] = 0;
+    }
+
+    dissect(graph, neighbors, left, side, order);
+    dissect(graph, neighbors, right, side, order);
+    order.extend(separator);
+}
+
+pub struct CchQuery<'a> {
+    forward: Vec<Length>,
+    backward: Vec<Length>,
+    cch: &'a Cch,
+}
+
+impl<'a> CchQuery<'a> {
+    /// Distance from `s` to `t` by scanning the elimination tree ancestors of
+    /// both nodes, `usize::MAX` if `t` is unreachable.
+    pub fn distance(&mut self, s: NodeId, t: NodeId) -> Length {
+        let cch = self.cch;
+        let s = cch.rank[s];
+        let t = cch.rank[t];
+        cch.relax_ancestors(s, &mut self.forward, &cch.up_weight);
+        cch.relax_ancestors(t, &mut self.backward, &cch.down_weight);
+
+        let mut result = usize::MAX;
+        let mut v = t;
+        while v != NO_NODE {
+            result = result.min(self.forward[v].saturating_add(self.backward[v]));
+            v = cch.parent[v];
+        }
+
+        cch.reset_ancestors(s, &mut self.forward);
+        cch.reset_ancestors(t, &mut self.backward);
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ae1::{Graph, EdgeInfo, NodeInfo, RESIDENTIAL};
+
+    fn grid(size: usize) -> Graph {
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        for y in 0..size {
+            for x in 0..size {
+                let id = y * size + x;
+                nodes.push(NodeInfo::new(id, 48.0 + y as f64 * 0.01, 9.0 + x as f64 * 0.01, 0));
+                if x + 1 < size {
+                    edges.push(EdgeInfo::new(id, id + 1, 1 + (x * 7 + y) % 5, RESIDENTIAL, 30));
+                    edges.push(EdgeInfo::new(id + 1, id, 1 + (x + y * 3) % 4, RESIDENTIAL, 30));
+                }
+                if y + 1 < size && x % 3 != 1 {
+                    edges.push(EdgeInfo::new(id, id + size, 2 + (x * y) % 3, RESIDENTIAL, 30));
+                    edges.push(EdgeInfo::new(id + size, id, 2 + (x + y) % 6, RESIDENTIAL, 30));
+                }
+            }
+        }
+        Graph::new(nodes, edges)
+    }
+
+    #[test]
+    fn cch_equals_dijkstra() {
+        let mut g = grid(12);
+        let mut cch = super::Cch::new(&g);
+        cch.customize(&g.edge_weights());
+        {
+            let mut dijkstra = g.dijkstra();
+            let mut query = cch.query();
+            for s in (0..g.node_count()).step_by(7) {
+                for t in 0..g.node_count() {
+                    let expected = dijkstra.distance(s, t).map_or(usize::MAX, |(d, _)| d);
+                    assert_eq!(query.distance(s, t), expected, "from {} to {}", s, t);
+                }
+            }
+        }
+
+        let unit: Vec<_> = g.edge_weights().iter().map(|_| 1).collect();
+        cch.customize(&unit);
+        let updates: Vec<_> = (0..g.node_count())
+            .flat_map(|s| {
+                g.outgoing_edges_for(s)
+                    .iter()
+                    .map(|e| (s, e.endpoint(), 1))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        g.update_weights(&updates);
+        let mut dijkstra = g.dijkstra();
+        let mut query = cch.query();
+        for s in (3..g.node_count()).step_by(11) {
+            for t in 0..g.node_count() {
+                let expected = dijkstra.distance(s, t).map_or(usize::MAX, |(d, _)| d);
+                assert_eq!(query.distance(s, t), expected, "from {} to {}", s, t);
+            }
+        }
+    }
+}
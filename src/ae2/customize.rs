@@ -0,0 +1,358 @@
+use ae2::*;
+
+use std::collections::{HashMap, HashSet};
+
+impl ChGraph {
+    /// Sets new weights for original (non shortcut) edges, identified by their
+    /// position in the edge list of the file, and recomputes all shortcuts.
+    /// Weights can both increase and decrease.
+    pub fn update_weights(&mut self, updates: &[(EdgeId, Length)]) {
+        for &(id, weight) in updates {
+            assert!(
+                self.shortcuts[id].is_none(),
+                "edge {} is a shortcut and can't be updated directly",
+                id
+            );
+            self.set_weight(id, weight);
+        }
+        self.customize();
+    }
+
+    /// Recomputes the weight of every shortcut as the shortest path through
+    /// the nodes below both of its endpoints.
+    ///
+    /// Contraction skips the shortcuts a witness path makes unnecessary, and
+    /// an original edge `u -> w` is never lowered to a cheaper path over a
+    /// lower node. So the first customization adds a shortcut for every such
+    /// path, next to any edge that already connects its endpoints. Nodes are
+    /// then processed by ascending level and every path `u -> v -> w` over a
+    /// lower node `v` relaxes the shortcuts `u -> w`, which also take the two
+    /// edges of the cheapest path as their children. Lower shortcuts are
+    /// always final before they are used.
+    pub fn customize(&mut self) {
+        if !self.customizable {
+            self.add_candidate_shortcuts();
+        }
+        let mut in_ids = vec![0; self.in_index.len()];
+        for (id, &position) in self.in_index.iter().enumerate() {
+            in_ids[position] = id;
+        }
+        let mut out_ids = vec![0; self.out_index.len()];
+        for (id, &position) in self.out_index.iter().enumerate() {
+            out_ids[position] = id;
+        }
+        for id in 0..self.edge_count() {
+            if self.is_shortcut(id) {
+                self.set_weight(id, Length::MAX);
+            }
+        }
+
+        for v in self.node_order() {
+            let key = (self.level[v], v);
+            let upper = |edges: &[HalfEdge], ids: &[EdgeId]| -> Vec<(NodeId, Length, EdgeId)> {
+                edges
+                    .iter()
+                    .zip(ids)
+                    .filter(|&(e, _)| (self.level[e.endpoint], e.endpoint) > key)
+                    .map(|(e, &id)| (e.endpoint, e.weight, id))
+                    .collect()
+            };
+            let in_range = self.node_offsets[v].in_start..self.node_offsets[v + 1].in_start;
+            let out_range = self.node_offsets[v].out_start..self.node_offsets[v + 1].out_start;
+            let ins = upper(&self.in_edges[in_range.clone()], &in_ids[in_range]);
+            let outs = upper(&self.out_edges[out_range.clone()], &out_ids[out_range]);
+            for &(u, in_weight, in_id) in &ins {
+                let out_start = self.node_offsets[u].out_start;
+                for &(w, out_weight, out_id) in outs.iter().filter(|&&(w, _, _)| w != u) {
+                    let weight = in_weight.saturating_add(out_weight);
+                    let first = self.outgoing_edges_for(u).partition_point(|e| e.endpoint < w);
+                    let mut position = out_start + first;
+                    while position < self.node_offsets[u + 1].out_start &&
+                        self.out_edges[position].endpoint == w
+                    {
+                        let id = out_ids[position];
+                        if self.is_shortcut(id) && weight < self.weight(id) {
+                            self.set_weight(id, weight);
+                            self.shortcuts[id] = Some((in_id, out_id));
+                        }
+                        position += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Adds a shortcut `u -> w` for every path `u -> v -> w` over a node `v`
+    /// below `u` and `w`, as long as there is no shortcut `u -> w` yet. New
+    /// shortcuts get the ids after the existing edges.
+    fn add_candidate_shortcuts(&mut self) {
+        let node_count = self.node_count();
+        let edge_count = self.edge_count();
+        let order = self.node_order();
+        let mut rank = vec![0; node_count];
+        for (i, &node) in order.iter().enumerate() {
+            rank[node] = i;
+        }
+
+        let mut arcs = HashMap::new();
+        let mut shortcut_arcs = HashSet::new();
+        for id in 0..edge_count {
+            let arc = (self.source(id), self.dest(id));
+            arcs.entry(arc).or_insert(id);
+            if self.is_shortcut(id) {
+                shortcut_arcs.insert(arc);
+            }
+        }
+        let mut up_in = vec![Vec::new(); node_count];
+        let mut up_out = vec![Vec::new(); node_count];
+        for &(u, w) in arcs.keys() {
+            if rank[u] < rank[w] {
+                up_out[u].push(w);
+            } else if rank[w] < rank[u] {
+                up_in[w].push(u);
+            }
+        }
+
+        let mut edges: Vec<ChEdgeInfo> = (0..edge_count)
+            .map(|id| {
+                let (a, b) = match self.shortcuts[id] {
+                    Some((a, b)) => (Some(a), Some(b)),
+                    None => (None, None),
+                };
+                let road_type = self.out_edges[self.out_index[id]].road_type;
+                let (source, dest, weight) = self.edge(id);
+                ChEdgeInfo::new(source, dest, weight, road_type, 0, a, b)
+            })
+            .collect();
+        for v in order {
+            let ins = ::std::mem::take(&mut up_in[v]);
+            let outs = ::std::mem::take(&mut up_out[v]);
+            for &u in &ins {
+                for &w in outs.iter().filter(|&&w| w != u) {
+                    if !shortcut_arcs.insert((u, w)) {
+                        continue;
+                    }
+                    // customize picks the children of the cheapest path
+                    let children = (Some(arcs[&(u, v)]), Some(arcs[&(v, w)]));
+                    edges.push(ChEdgeInfo::new(u, w, Length::MAX, 0, 0, children.0, children.1));
+                    if arcs.contains_key(&(u, w)) {
+                        continue;
+                    }
+                    arcs.insert((u, w), edges.len() - 1);
+                    if rank[u] < rank[w] {
+                        up_out[u].push(w);
+                    } else {
+                        up_in[w].push(u);
+                    }
+                }
+            }
+        }
+
+        let node_info = ::std::mem::take(&mut self.node_info);
+        *self = ChGraph::new(node_info, edges);
+        self.customizable = true;
+    }
+
+    /// All nodes by ascending level, ties broken by id.
+    fn node_order(&self) -> Vec<NodeId> {
+        let mut order: Vec<NodeId> = (0..self.node_count()).collect();
+        order.sort_by_key(|&node| (self.level[node], node));
+        order
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.shortcuts.len()
+    }
+
+    pub fn is_shortcut(&self, id: EdgeId) -> bool {
+        self.shortcuts[id].is_some()
+    }
+
+    /// Source, destination and weight of the edge with the given file index.
+    pub fn edge(&self, id: EdgeId) -> (NodeId, NodeId, Length) {
+        (self.source(id), self.dest(id), self.weight(id))
+    }
+
+    fn weight(&self, id: EdgeId) -> Length {
+        self.out_edges[self.out_index[id]].weight
+    }
+
+    fn set_weight(&mut self, id: EdgeId, weight: Length) {
+        self.out_edges[self.out_index[id]].weight = weight;
+        self.in_edges[self.in_index[id]].weight = weight;
+    }
+
+    fn source(&self, id: EdgeId) -> NodeId {
+        self.in_edges[self.in_index[id]].endpoint
+    }
+
+    fn dest(&self, id: EdgeId) -> NodeId {
+        self.out_edges[self.out_index[id]].endpoint
+    }
+}
+
+#[test]
+fn customize_shortcuts() {
+    // 3 -> 0 -> 1 -> 2 contracted in this order, with 1 -> 2 also reachable
+    // directly from 3
+    let nodes = (0..4)
+        .map(|id| ChNodeInfo::new(id, id, 48.0, 9.0, 0, id))
+        .collect();
+    let edges = vec![
+        ChEdgeInfo::new(3, 0, 2, 12, 30, None, None),
+        ChEdgeInfo::new(0, 1, 3, 12, 30, None, None),
+        ChEdgeInfo::new(1, 2, 4, 12, 30, None, None),
+        ChEdgeInfo::new(3, 1, 5, 0, 0, Some(0), Some(1)),
+        ChEdgeInfo::new(3, 2, 9, 0, 0, Some(3), Some(2)),
+        ChEdgeInfo::new(3, 2, 12, 12, 30, None, None),
+    ];
+    let mut g = ChGraph::new(nodes, edges);
+    assert_eq!(g.dijkstra().distance(3, 2), 9);
+
+    g.update_weights(&[(0, 4), (2, 1)]);
+    assert_eq!(g.weight(3), 7);
+    assert_eq!(g.weight(4), 8);
+    assert_eq!(g.dijkstra().distance(3, 2), 8);
+
+    g.update_weights(&[(1, 10)]);
+    assert_eq!(g.weight(4), 15);
+    assert_eq!(g.dijkstra().distance(3, 2), 12);
+}
+
+#[test]
+fn customize_broken_witness() {
+    // contracting 0 skips the shortcut 1 -> 2 because of the witness 1 -> 3 -> 2
+    let nodes = (0..4)
+        .map(|id| ChNodeInfo::new(id, id, 48.0, 9.0, 0, id))
+        .collect();
+    let edges = vec![
+        ChEdgeInfo::new(1, 0, 1, 12, 30, None, None),
+        ChEdgeInfo::new(0, 2, 1, 12, 30, None, None),
+        ChEdgeInfo::new(1, 3, 1, 12, 30, None, None),
+        ChEdgeInfo::new(3, 2, 1, 12, 30, None, None),
+    ];
+    let mut g = ChGraph::new(nodes, edges);
+    assert_eq!(g.dijkstra().distance(1, 2), 2);
+
+    g.update_weights(&[(3, 10)]);
+    assert_eq!(g.edge_count(), 5);
+    assert!(g.is_shortcut(4));
+    assert_eq!(g.edge(4), (1, 2, 2));
+    assert_eq!(g.dijkstra().distance(1, 2), 2);
+
+    g.update_weights(&[(0, 20)]);
+    assert_eq!(g.dijkstra().distance(1, 2), 11);
+}
+
+#[test]
+fn customize_lowers_original_edges() {
+    // 1 -> 2 is an original edge, the path over 0 only gets cheaper later
+    let nodes = (0..3)
+        .map(|id| ChNodeInfo::new(id, id, 48.0, 9.0, 0, id))
+        .collect();
+    let edges = vec![
+        ChEdgeInfo::new(1, 0, 3, 12, 30, None, None),
+        ChEdgeInfo::new(0, 2, 3, 12, 30, None, None),
+        ChEdgeInfo::new(1, 2, 5, 12, 30, None, None),
+    ];
+    let mut g = ChGraph::new(nodes, edges);
+    g.update_weights(&[(0, 1)]);
+    assert_eq!(g.dijkstra().distance(1, 2), 4);
+}
+
+#[test]
+fn customize_unpacks_cheapest_path() {
+    // the shortcut 2 -> 3 over 0 has the witness 2 -> 1 -> 3
+    let nodes = (0..4)
+        .map(|id| ChNodeInfo::new(id, id, 48.0, 9.0, 0, id))
+        .collect();
+    let edges = vec![
+        ChEdgeInfo::new(2, 0, 1, 12, 30, None, None),
+        ChEdgeInfo::new(0, 3, 1, 12, 30, None, None),
+        ChEdgeInfo::new(2, 1, 2, 12, 30, None, None),
+        ChEdgeInfo::new(1, 3, 2, 12, 30, None, None),
+        ChEdgeInfo::new(2, 3, 2, 0, 0, Some(0), Some(1)),
+    ];
+    let mut g = ChGraph::new(nodes, edges);
+    g.update_weights(&[(0, 50)]);
+    assert!(g.validate().is_ok());
+
+    fn unpack(g: &ChGraph, id: EdgeId, path: &mut Vec<EdgeId>) {
+        match g.shortcuts[id] {
+            Some((a, b)) => {
+                unpack(g, a, path);
+                unpack(g, b, path);
+            }
+            None => path.push(id),
+        }
+    }
+    let mut path = Vec::new();
+    unpack(&g, 4, &mut path);
+    assert_eq!(path, vec![2, 3]);
+    let length: Length = path.iter().map(|&id| g.weight(id)).sum();
+    assert_eq!(length, g.dijkstra().distance(2, 3));
+}
+
+#[test]
+fn customize_equals_dijkstra() {
+    use ae1::{Graph, EdgeInfo, NodeInfo, RESIDENTIAL};
+
+    let size = 6;
+    let node_count = size * size;
+    let mut arcs = Vec::new();
+    for y in 0..size {
+        for x in 0..size {
+            let id = y * size + x;
+            if x + 1 < size {
+                arcs.push((id, id + 1, 1 + (x * 7 + y) % 5));
+                arcs.push((id + 1, id, 1 + (x + y * 3) % 4));
+            }
+            if y + 1 < size {
+                arcs.push((id, id + size, 2 + (x * y) % 3));
+                arcs.push((id + size, id, 2 + (x + y) % 6));
+            }
+        }
+    }
+    let mut g = Graph::new(
+        (0..node_count)
+            .map(|id| NodeInfo::new(id, 48.0, 9.0, 0))
+            .collect(),
+        arcs.iter()
+            .map(|&(s, t, w)| EdgeInfo::new(s, t, w, RESIDENTIAL, 30))
+            .collect(),
+    );
+    // levels scattered over the grid, so most nodes are below some neighbour
+    let mut ch = ChGraph::new(
+        (0..node_count)
+            .map(|id| ChNodeInfo::new(id, id, 48.0, 9.0, 0, id * 7 % node_count))
+            .collect(),
+        arcs.iter()
+            .map(|&(s, t, w)| ChEdgeInfo::new(s, t, w, RESIDENTIAL, 30, None, None))
+            .collect(),
+    );
+
+    let mut seed: usize = 17;
+    for _ in 0..5 {
+        let mut ch_updates = Vec::new();
+        let mut updates = Vec::new();
+        for _ in 0..20 {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let id = (seed >> 33) % arcs.len();
+            let weight = 1 + (seed >> 17) % 20;
+            ch_updates.push((id, weight));
+            updates.push((arcs[id].0, arcs[id].1, weight));
+        }
+        ch.update_weights(&ch_updates);
+        g.update_weights(&updates);
+        assert_eq!(ch.validate().err(), None);
+
+        let mut dijkstra = g.dijkstra();
+        let mut query = ch.dijkstra();
+        for s in 0..node_count {
+            for t in 0..node_count {
+                let expected = dijkstra.distance(s, t).map_or(usize::MAX, |(d, _)| d);
+                assert_eq!(query.distance(s, t), expected, "from {} to {}", s, t);
+            }
+        }
+    }
+}
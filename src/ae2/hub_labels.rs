@@ -0,0 +1,548 @@
+use ae2::*;
+use ae1::{read_varint, write_varint};
+
+use rayon::prelude::*;
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"HUBL";
+
+/// Labels of all nodes in one direction, sorted by hub.
+#[derive(HeapSizeOf, Debug, PartialEq)]
+struct Labels {
+    first: Vec<usize>,
+    hubs: Vec<NodeId>,
+    dists: Vec<Length>,
+}
+
+impl Labels {
+    fn from_lists(lists: Vec<Vec<(NodeId, Length)>>) -> Labels {
+        let mut labels = Labels {
+            first: Vec::with_capacity(lists.len() + 1),
+            hubs: Vec::new(),
+            dists: Vec::new(),
+        };
+        for list in lists {
+            labels.first.push(labels.hubs.len());
+            for (hub, dist) in list {
+                labels.hubs.push(hub);
+                labels.dists.push(dist);
+            }
+        }
+        labels.first.push(labels.hubs.len());
+        labels
+    }
+
+    fn label(&self, node: NodeId) -> (&[NodeId], &[Length]) {
+        let range = self.first[node]..self.first[node + 1];
+        (&self.hubs[range.clone()], &self.dists[range])
+    }
+
+    fn compress(&self) -> CompressedLabels {
+        let mut compressed = CompressedLabels {
+            first: Vec::with_capacity(self.first.len()),
+            bytes: Vec::new(),
+        };
+        for node in 0..self.first.len() - 1 {
+            compressed.first.push(compressed.bytes.len());
+            let (hubs, dists) = self.label(node);
+            write_varint(&mut compressed.bytes, hubs.len() as u64);
+            let mut previous = 0;
+            for (&hub, &dist) in hubs.iter().zip(dists) {
+                write_varint(&mut compressed.bytes, (hub - previous) as u64);
+                write_varint(&mut compressed.bytes, dist as u64);
+                previous = hub;
+            }
+        }
+        compressed.first.push(compressed.bytes.len());
+        compressed
+    }
+}
+
+/// Number of entries `CompressedLabels::read_from` reserves up front at most.
+const READ_CAPACITY: usize = 1 << 20;
+
+/// Labels of all nodes in one direction with the hubs as differences to the
+/// previous hub and everything encoded as varints.
+#[derive(HeapSizeOf, Debug, PartialEq)]
+struct CompressedLabels {
+    first: Vec<usize>,
+    bytes: Vec<u8>,
+}
+
+impl CompressedLabels {
+    fn label(&self, node: NodeId) -> LabelIter<'_> {
+        let mut position = self.first[node];
+        let remaining = read_varint(&self.bytes, &mut position) as usize;
+        LabelIter {
+            bytes: &self.bytes,
+            position,
+            remaining,
+            hub: 0,
+        }
+    }
+
+    fn decompress(&self) -> Labels {
+        Labels::from_lists(
+            (0..self.first.len() - 1)
+                .map(|node| self.label(node).collect())
+                .collect(),
+        )
+    }
+
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&(self.first.len() as u64).to_le_bytes())?;
+        for &first in &self.first {
+            writer.write_all(&(first as u64).to_le_bytes())?;
+        }
+        writer.write_all(&self.bytes)
+    }
+
+    /// Reads labels written by `write_to`. The sizes in the file aren't
+    /// trusted for allocations, the buffers only grow with the data read.
+    fn read_from<R: Read>(reader: &mut R) -> io::Result<CompressedLabels> {
+        let len = read_u64(reader)? as usize;
+        let mut first = Vec::with_capacity(len.min(READ_CAPACITY));
+        for _ in 0..len {
+            first.push(read_u64(reader)? as usize);
+        }
+        if len == 0 || first.windows(2).any(|w| w[0] > w[1]) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Broken label offsets"));
+        }
+        let byte_count = first[len - 1] as u64;
+        let mut bytes = Vec::with_capacity(first[len - 1].min(READ_CAPACITY));
+        reader.take(byte_count).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 != byte_count {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Labels are cut off"));
+        }
+        let labels = CompressedLabels { first, bytes };
+        if (0..len - 1).any(|node| labels.check_label(node).is_none()) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Broken labels"));
+        }
+        Ok(labels)
+    }
+
+    /// Decodes a label with bounds checks, `None` unless it fills exactly
+    /// its bytes and holds ascending hubs of existing nodes.
+    fn check_label(&self, node: NodeId) -> Option<()> {
+        let bytes = &self.bytes[..self.first[node + 1]];
+        let mut position = self.first[node];
+        let count = checked_varint(bytes, &mut position)?;
+        let mut hub: u64 = 0;
+        for i in 0..count {
+            let delta = checked_varint(bytes, &mut position)?;
+            if i > 0 && delta == 0 {
+                return None;
+            }
+            hub = hub.checked_add(delta)?;
+            if hub >= (self.first.len() - 1) as u64 {
+                return None;
+            }
+            checked_varint(bytes, &mut position)?;
+        }
+        if position == bytes.len() { Some(()) } else { None }
+    }
+}
+
+/// `read_varint` for untrusted bytes, `None` if the varint is cut off or
+/// too long.
+fn checked_varint(bytes: &[u8], position: &mut usize) -> Option<u64> {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *bytes.get(*position)?;
+        *position += 1;
+        if shift > 63 {
+            return None;
+        }
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte < 0x80 {
+            return Some(value);
+        }
+        shift += 7;
+    }
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut buffer = [0; 8];
+    reader.read_exact(&mut buffer)?;
+    Ok(u64::from_le_bytes(buffer))
+}
+
+/// Entries of a compressed label in order.
+struct LabelIter<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    remaining: usize,
+    hub: NodeId,
+}
+
+impl<'a> Iterator for LabelIter<'a> {
+    type Item = (NodeId, Length);
+
+    fn next(&mut self) -> Option<(NodeId, Length)> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.hub += read_varint(self.bytes, &mut self.position) as NodeId;
+        let dist = read_varint(self.bytes, &mut self.position) as Length;
+        Some((self.hub, dist))
+    }
+}
+
+/// Smallest sum of distances over the hubs two sorted labels share.
+fn merge<F, B>(forward: F, backward: B) -> Length
+where
+    F: IntoIterator<Item = (NodeId, Length)>,
+    B: IntoIterator<Item = (NodeId, Length)>,
+{
+    let mut best = usize::MAX;
+    let mut forward = forward.into_iter();
+    let mut backward = backward.into_iter();
+    let (mut f, mut b) = (forward.next(), backward.next());
+    while let (Some((f_hub, f_dist)), Some((b_hub, b_dist))) = (f, b) {
+        match f_hub.cmp(&b_hub) {
+            Ordering::Less => f = forward.next(),
+            Ordering::Greater => b = backward.next(),
+            Ordering::Equal => {
+                best = best.min(f_dist.saturating_add(b_dist));
+                f = forward.next();
+                b = backward.next();
+            }
+        }
+    }
+    best
+}
+
+fn entries<'a>(label: (&'a [NodeId], &'a [Length])) -> impl Iterator<Item = (NodeId, Length)> + 'a {
+    label.0.iter().cloned().zip(label.1.iter().cloned())
+}
+
+/// Hub labels derived from the hierarchy: the forward label of a node holds
+/// the distances to the nodes found by its upward search, the backward label
+/// the distances from the nodes found by its upward search on ingoing edges.
+#[derive(HeapSizeOf, Debug, PartialEq)]
+pub struct HubLabels {
+    forward: Labels,
+    backward: Labels,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+struct NodeCost {
+    node: NodeId,
+    cost: usize,
+}
+
+impl Ord for NodeCost {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.cmp(&self.cost)
+    }
+}
+
+impl PartialOrd for NodeCost {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl ChGraph {
+    /// All nodes of the upward search from `node` with their distance,
+    /// sorted by node id.
+    fn upward_search(&self, node: NodeId, forward: bool) -> Vec<(NodeId, Length)> {
+        let mut dist = ::std::collections::HashMap::new();
+        let mut heap = BinaryHeap::new();
+        dist.insert(node, 0);
+        heap.push(NodeCost { node, cost: 0 });
+        while let Some(NodeCost { node, cost }) = heap.pop() {
+            if cost > dist[&node] {
+                continue;
+            }
+            let edges = if forward {
+                self.outgoing_edges_for(node)
+            } else {
+                self.ingoing_edges_for(node)
+            };
+            for edge in edges {
+                if self.level[edge.endpoint] >= self.level[node] {
+                    let next = NodeCost {
+                        node: edge.endpoint,
+                        cost: cost + edge.weight,
+                    };
+                    if dist.get(&next.node).is_none_or(|&d| next.cost < d) {
+                        dist.insert(next.node, next.cost);
+                        heap.push(next);
+                    }
+                }
+            }
+        }
+        let mut label: Vec<(NodeId, Length)> = dist.into_iter().collect();
+        label.sort();
+        label
+    }
+}
+
+impl HubLabels {
+    /// Builds the labels with one upward search per node and direction. An
+    /// entry is pruned if the labels found by the searches already give a
+    /// shorter distance to its hub, as such a hub can't be the meeting node
+    /// of a shortest path.
+    pub fn new(graph: &ChGraph) -> HubLabels {
+        let nodes = graph.node_info.len();
+        let forward = Labels::from_lists(
+            (0..nodes)
+                .into_par_iter()
+                .map(|node| graph.upward_search(node, true))
+                .collect(),
+        );
+        let backward = Labels::from_lists(
+            (0..nodes)
+                .into_par_iter()
+                .map(|node| graph.upward_search(node, false))
+                .collect(),
+        );
+
+        let forward_pruned = (0..nodes)
+            .into_par_iter()
+            .map(|node| {
+                entries(forward.label(node))
+                    .filter(|&(hub, dist)| {
+                        merge(entries(forward.label(node)), entries(backward.label(hub))) >= dist
+                    })
+                    .collect()
+            })
+            .collect();
+        let backward_pruned = (0..nodes)
+            .into_par_iter()
+            .map(|node| {
+                entries(backward.label(node))
+                    .filter(|&(hub, dist)| {
+                        merge(entries(forward.label(hub)), entries(backward.label(node))) >= dist
+                    })
+                    .collect()
+            })
+            .collect();
+
+        HubLabels {
+            forward: Labels::from_lists(forward_pruned),
+            backward: Labels::from_lists(backward_pruned),
+        }
+    }
+
+    /// Length of the shortest path from `s` to `t`, `usize::MAX` if there is
+    /// none, like `ChDijkstra::distance`.
+    pub fn distance(&self, s: NodeId, t: NodeId) -> Length {
+        merge(entries(self.forward.label(s)), entries(self.backward.label(t)))
+    }
+
+    pub fn average_label_size(&self) -> f64 {
+        let nodes = self.forward.first.len() - 1;
+        (self.forward.hubs.len() + self.backward.hubs.len()) as f64 / (2 * nodes.max(1)) as f64
+    }
+
+    pub fn compress(&self) -> CompressedHubLabels {
+        CompressedHubLabels {
+            forward: self.forward.compress(),
+            backward: self.backward.compress(),
+        }
+    }
+}
+
+/// Varint encoded `HubLabels`, about a quarter of the size. Queries decode
+/// the two labels on the fly.
+#[derive(HeapSizeOf, Debug, PartialEq)]
+pub struct CompressedHubLabels {
+    forward: CompressedLabels,
+    backward: CompressedLabels,
+}
+
+impl CompressedHubLabels {
+    pub fn distance(&self, s: NodeId, t: NodeId) -> Length {
+        merge(self.forward.label(s), self.backward.label(t))
+    }
+
+    pub fn decompress(&self) -> HubLabels {
+        HubLabels {
+            forward: self.forward.decompress(),
+            backward: self.backward.decompress(),
+        }
+    }
+
+    /// Writes the labels in a binary format: the magic bytes `HUBL`, then for
+    /// the forward and the backward labels the number of offsets, the
+    /// offsets and the encoded labels, all integers as little endian `u64`.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        self.forward.write_to(writer)?;
+        self.backward.write_to(writer)
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<CompressedHubLabels> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Not a hub label file"));
+        }
+        let forward = CompressedLabels::read_from(reader)?;
+        let backward = CompressedLabels::read_from(reader)?;
+        if forward.first.len() != backward.first.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Forward and backward labels differ in node count",
+            ));
+        }
+        Ok(CompressedHubLabels { forward, backward })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, file: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(file)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(file: P) -> io::Result<CompressedHubLabels> {
+        CompressedHubLabels::read_from(&mut BufReader::new(File::open(file)?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ae2::*;
+    use super::*;
+
+    /// Contracts the nodes by id, adding a shortcut for every pair of higher
+    /// neighbors unless there already is an edge at most as long.
+    fn contract(size: usize) -> ChGraph {
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        for y in 0..size {
+            for x in 0..size {
+                // mix the ids so the level order isn't the grid order
+                let id = (y * size + x) * 7 % (size * size);
+                nodes.push((id, y, x));
+            }
+        }
+        let at = |x: usize, y: usize| nodes[y * size + x].0;
+        for y in 0..size {
+            for x in 0..size {
+                if x + 1 < size {
+                    edges.push(ChEdgeInfo::new(at(x, y), at(x + 1, y), 1 + (x * 7 + y) % 5, 12, 30, None, None));
+                    edges.push(ChEdgeInfo::new(at(x + 1, y), at(x, y), 1 + (x + y * 3) % 4, 12, 30, None, None));
+                }
+                if y + 1 < size && x % 3 != 1 {
+                    edges.push(ChEdgeInfo::new(at(x, y), at(x, y + 1), 2 + (x * y) % 3, 12, 30, None, None));
+                    edges.push(ChEdgeInfo::new(at(x, y + 1), at(x, y), 2 + (x + y) % 6, 12, 30, None, None));
+                }
+            }
+        }
+        for node in 0..size * size {
+            let ins: Vec<EdgeId> = (0..edges.len())
+                .filter(|&e| edges[e].dest == node && edges[e].source > node)
+                .collect();
+            let outs: Vec<EdgeId> = (0..edges.len())
+                .filter(|&e| edges[e].source == node && edges[e].dest > node)
+                .collect();
+            for &a in &ins {
+                for &b in &outs {
+                    let (source, dest) = (edges[a].source, edges[b].dest);
+                    let length = edges[a].length + edges[b].length;
+                    let known = edges.iter().any(|e| {
+                        e.source == source && e.dest == dest && e.length <= length
+                    });
+                    if source != dest && !known {
+                        let shortcut = ChEdgeInfo::new(
+                            source,
+                            dest,
+                            length,
+                            0,
+                            0,
+                            Some(a),
+                            Some(b),
+                        );
+                        edges.push(shortcut);
+                    }
+                }
+            }
+        }
+        let mut info: Vec<ChNodeInfo> = nodes
+            .iter()
+            .map(|&(id, y, x)| ChNodeInfo::new(id, id, y as f64, x as f64, 0, id))
+            .collect();
+        info.sort_by_key(|n| n.id);
+        ChGraph::new(info, edges)
+    }
+
+    #[test]
+    fn labels_equal_ch_queries() {
+        let g = contract(6);
+        let stats = g.stats();
+        assert_eq!(stats.level_violations + stats.length_mismatches + stats.disconnected_children, 0);
+        let labels = HubLabels::new(&g);
+        let compressed = labels.compress();
+        let mut dijkstra = g.dijkstra();
+        for s in 0..36 {
+            for t in 0..36 {
+                let expected = dijkstra.distance(s, t);
+                assert_eq!(labels.distance(s, t), expected, "from {} to {}", s, t);
+                assert_eq!(compressed.distance(s, t), expected, "from {} to {}", s, t);
+            }
+        }
+
+        let unpruned: usize = (0..36).map(|n| g.upward_search(n, true).len()).sum();
+        assert!(labels.forward.hubs.len() < unpruned);
+        assert_eq!(compressed.decompress(), labels);
+    }
+
+    #[test]
+    fn serialization_round_trip() {
+        let labels = HubLabels::new(&contract(4));
+        let compressed = labels.compress();
+        let mut buffer = Vec::new();
+        compressed.write_to(&mut buffer).unwrap();
+        assert_eq!(&buffer[..4], b"HUBL");
+        let read = CompressedHubLabels::read_from(&mut &buffer[..]).unwrap();
+        assert_eq!(read, compressed);
+
+        buffer[0] = b'X';
+        assert!(CompressedHubLabels::read_from(&mut &buffer[..]).is_err());
+        assert!(CompressedHubLabels::read_from(&mut &buffer[4..20]).is_err());
+
+        // huge sizes in a corrupt header fail when the data runs out
+        let mut corrupt = b"HUBL".to_vec();
+        corrupt.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert!(CompressedHubLabels::read_from(&mut &corrupt[..]).is_err());
+        let mut corrupt = b"HUBL".to_vec();
+        corrupt.extend_from_slice(&1u64.to_le_bytes());
+        corrupt.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert!(CompressedHubLabels::read_from(&mut &corrupt[..]).is_err());
+    }
+
+    #[test]
+    fn corrupted_labels() {
+        let compressed = HubLabels::new(&contract(4)).compress();
+        let mut buffer = Vec::new();
+        compressed.write_to(&mut buffer).unwrap();
+        let labels_start = 4 + 8 * (compressed.forward.first.len() + 1);
+
+        // a continuation bit running into the next label
+        let mut corrupt = buffer.clone();
+        corrupt[labels_start] |= 0x80;
+        assert!(CompressedHubLabels::read_from(&mut &corrupt[..]).is_err());
+
+        // a hub beyond the last node
+        let mut corrupt = buffer.clone();
+        corrupt[labels_start + 1] = 0x7f;
+        assert!(CompressedHubLabels::read_from(&mut &corrupt[..]).is_err());
+
+        // backward labels of a different node count
+        let mut labels = HubLabels::new(&contract(4)).compress();
+        labels.backward = HubLabels::new(&contract(3)).compress().backward;
+        let mut corrupt = Vec::new();
+        labels.write_to(&mut corrupt).unwrap();
+        assert!(CompressedHubLabels::read_from(&mut &corrupt[..]).is_err());
+    }
+}
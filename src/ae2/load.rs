@@ -6,7 +6,7 @@ use std::path::Path;
 use std::fs::File;
 use std::io::Read;
 
-fn load_file<P: AsRef<Path>>(file: P) -> (Vec<ChNodeInfo>, Vec<ChEdgeInfo>) {
+pub(super) fn load_file<P: AsRef<Path>>(file: P) -> (Vec<ChNodeInfo>, Vec<ChEdgeInfo>) {
     let mut buffer = String::new();
 
     let mut file = File::open(file).expect("File could not be opened");
@@ -77,7 +77,11 @@ fn load_file<P: AsRef<Path>>(file: P) -> (Vec<ChNodeInfo>, Vec<ChEdgeInfo>) {
                 .map(str::parse)
                 .expect("No length found")
                 .expect("Length id not parse-able");
-            raw_node_data.next(); //ignore type
+            // shortcuts have the type -1, they get no road type
+            let road_type: RoadType = match raw_node_data.next().expect("No type found") {
+                "-1" => 0,
+                raw_type => raw_type.parse().expect("Type not parse-able"),
+            };
             let speed: Speed = raw_node_data
                 .next()
                 .map(str::parse)
@@ -93,7 +97,7 @@ fn load_file<P: AsRef<Path>>(file: P) -> (Vec<ChNodeInfo>, Vec<ChEdgeInfo>) {
                 .map(str::parse)
                 .expect("No edgeb found")
                 .ok();
-            ChEdgeInfo::new(source, dest, length, speed, edge_a, edge_b)
+            ChEdgeInfo::new(source, dest, length, road_type, speed, edge_a, edge_b)
 
         })
         .collect();
@@ -1,7 +1,15 @@
 mod load;
 mod alg;
+mod customize;
+mod cch;
+mod validate;
+mod reorder;
+mod hub_labels;
 
 pub use self::load::load_graph;
+pub use self::cch::Cch;
+pub use self::validate::{ChStats, check_ch_file};
+pub use self::hub_labels::{HubLabels, CompressedHubLabels};
 
 pub type NodeId = usize;
 pub type EdgeId = usize;
@@ -12,6 +20,7 @@ pub type Length = usize;
 pub type Speed = usize;
 pub type Height = usize;
 pub type Level = usize;
+pub type RoadType = u8;
 
 #[derive(HeapSizeOf)]
 pub struct ChNodeInfo {
@@ -48,6 +57,7 @@ pub struct ChEdgeInfo {
     source: NodeId,
     dest: NodeId,
     length: Length,
+    road_type: RoadType,
     speed: Speed,
     edge_a: Option<EdgeId>,
     edge_b: Option<EdgeId>,
@@ -58,6 +68,7 @@ impl ChEdgeInfo {
         source: NodeId,
         dest: NodeId,
         length: Length,
+        road_type: RoadType,
         speed: Speed,
         edge_a: Option<EdgeId>,
         edge_b: Option<EdgeId>,
@@ -66,6 +77,7 @@ impl ChEdgeInfo {
             source: source,
             dest: dest,
             length: length,
+            road_type: road_type,
             speed: speed,
             edge_a: edge_a,
             edge_b: edge_b,
@@ -77,6 +89,15 @@ impl ChEdgeInfo {
 pub struct HalfEdge {
     endpoint: NodeId,
     weight: Length,
+    road_type: RoadType,
+}
+
+impl HalfEdge {
+    /// The road type of the edge, 0 for shortcuts.
+    #[allow(dead_code)]
+    pub fn road_type(&self) -> RoadType {
+        self.road_type
+    }
 }
 
 
@@ -102,6 +123,12 @@ pub struct ChGraph {
     out_edges: Vec<HalfEdge>,
     in_edges: Vec<HalfEdge>,
     level: Vec<Level>,
+    shortcuts: Vec<Option<(EdgeId, EdgeId)>>,
+    out_index: Vec<usize>,
+    in_index: Vec<usize>,
+    /// Whether a shortcut has been added for every path over a lower node,
+    /// which `customize` needs to stay correct.
+    customizable: bool,
 }
 
 enum OffsetMode {
@@ -111,7 +138,15 @@ enum OffsetMode {
 impl ChGraph {
     pub fn new(node_info: Vec<ChNodeInfo>, edges: Vec<ChEdgeInfo>) -> ChGraph {
         let level = node_info.iter().map(|n| n.level).collect();
-
+        let shortcuts = edges
+            .iter()
+            .map(|e| match (e.edge_a, e.edge_b) {
+                (Some(a), Some(b)) => Some((a, b)),
+                _ => None,
+            })
+            .collect();
+        let in_index = ChGraph::edge_positions(&edges, |e| (e.dest, e.source));
+        let out_index = ChGraph::edge_positions(&edges, |e| (e.source, e.dest));
 
         let node_count = node_info.len();
         let (node_offset, in_edges, out_edges) = ChGraph::calc_node_offsets(node_count, edges);
@@ -121,10 +156,26 @@ impl ChGraph {
             out_edges: out_edges,
             in_edges: in_edges,
             level: level,
+            shortcuts: shortcuts,
+            out_index: out_index,
+            in_index: in_index,
+            customizable: false,
         }
 
     }
 
+    /// Position of every edge of the input list after a stable sort by `key`,
+    /// which is where `calc_node_offsets` puts its half edge.
+    fn edge_positions<K: Ord, F: Fn(&ChEdgeInfo) -> K>(edges: &[ChEdgeInfo], key: F) -> Vec<usize> {
+        let mut order: Vec<EdgeId> = (0..edges.len()).collect();
+        order.sort_by_key(|&id| key(&edges[id]));
+        let mut positions = vec![0; edges.len()];
+        for (position, id) in order.into_iter().enumerate() {
+            positions[id] = position;
+        }
+        positions
+    }
+
     pub fn outgoing_edges_for(&self, id: NodeId) -> &[HalfEdge] {
         &self.out_edges[self.node_offsets[id].out_start..self.node_offsets[id + 1].out_start]
     }
@@ -213,6 +264,7 @@ impl ChGraph {
                         HalfEdge {
                             endpoint: e.source,
                             weight: e.length,
+                            road_type: e.road_type,
                         }
                     })
                     .collect()
@@ -225,6 +277,7 @@ impl ChGraph {
                         HalfEdge {
                             endpoint: e.dest,
                             weight: e.length,
+                            road_type: e.road_type,
                         }
                     })
                     .collect()
@@ -234,6 +287,6 @@ impl ChGraph {
     }
 
     pub fn node_count(&self) -> usize {
-        self.node_offsets.len()
+        self.node_info.len()
     }
 }
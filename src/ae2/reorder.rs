@@ -0,0 +1,73 @@
+use ae2::*;
+use ae1::NodeMapping;
+
+impl ChGraph {
+    /// Renumbers the nodes by descending level, so the nodes at the top of
+    /// the hierarchy, which are visited by most queries, are stored next to
+    /// each other. Edge ids stay the same.
+    pub fn reordered_by_level(&self) -> (ChGraph, NodeMapping) {
+        let mut order: Vec<NodeId> = (0..self.node_info.len()).collect();
+        order.sort_by_key(|&id| (::std::cmp::Reverse(self.level[id]), id));
+        let mapping = NodeMapping::from_order(order);
+
+        let nodes = (0..self.node_info.len())
+            .map(|id| {
+                let node = &self.node_info[mapping.original(id)];
+                ChNodeInfo::new(id, node.osm_id, node.lat, node.long, node.height, node.level)
+            })
+            .collect();
+        let edges = (0..self.edge_count())
+            .map(|id| {
+                let (source, dest, length) = self.edge(id);
+                let road_type = self.out_edges[self.out_index[id]].road_type;
+                let (edge_a, edge_b) = match self.shortcuts[id] {
+                    Some((a, b)) => (Some(a), Some(b)),
+                    None => (None, None),
+                };
+                ChEdgeInfo::new(
+                    mapping.new_id(source),
+                    mapping.new_id(dest),
+                    length,
+                    road_type,
+                    0,
+                    edge_a,
+                    edge_b,
+                )
+            })
+            .collect();
+        (ChGraph::new(nodes, edges), mapping)
+    }
+}
+
+#[test]
+fn level_order() {
+    let nodes = (0..4)
+        .map(|id| ChNodeInfo::new(id, id, 48.0, 9.0, 0, id))
+        .collect();
+    let edges = vec![
+        ChEdgeInfo::new(3, 0, 2, 12, 30, None, None),
+        ChEdgeInfo::new(0, 1, 3, 12, 30, None, None),
+        ChEdgeInfo::new(1, 2, 4, 12, 30, None, None),
+        ChEdgeInfo::new(3, 1, 5, 0, 0, Some(0), Some(1)),
+        ChEdgeInfo::new(3, 2, 9, 0, 0, Some(3), Some(2)),
+        ChEdgeInfo::new(3, 2, 12, 12, 30, None, None),
+    ];
+    let g = ChGraph::new(nodes, edges);
+    let (h, mapping) = g.reordered_by_level();
+    assert_eq!(mapping.original(0), 3);
+    assert_eq!(mapping.new_id(0), 3);
+    assert_eq!(h.level, vec![3, 2, 1, 0]);
+    assert_eq!(h.edge(4), (0, 1, 9));
+    assert!(h.stats().problems().is_empty());
+
+    let mut original = g.dijkstra();
+    let mut reordered = h.dijkstra();
+    for s in 0..4 {
+        for t in 0..4 {
+            assert_eq!(
+                reordered.distance(mapping.new_id(s), mapping.new_id(t)),
+                original.distance(s, t)
+            );
+        }
+    }
+}
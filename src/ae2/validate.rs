@@ -0,0 +1,187 @@
+use ae2::*;
+use ae1::GraphStats;
+use super::load::load_file;
+
+use std::fmt;
+use std::path::Path;
+
+/// `GraphStats` of the graph with all shortcuts plus the consistency of the
+/// hierarchy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChStats {
+    pub graph: GraphStats,
+    pub shortcuts: usize,
+    pub max_level: Level,
+    /// Shortcuts with only one or non existing child edges.
+    pub missing_children: usize,
+    /// Shortcuts whose children don't form a path from its source to its
+    /// destination.
+    pub disconnected_children: usize,
+    /// Shortcuts whose length isn't the sum of their children's lengths.
+    pub length_mismatches: usize,
+    /// Shortcuts bypassing a node that isn't below both of their endpoints.
+    pub level_violations: usize,
+}
+
+impl ChStats {
+    pub fn collect(nodes: &[ChNodeInfo], edges: &[ChEdgeInfo]) -> ChStats {
+        let coordinates: Vec<(Latitude, Longitude)> = nodes.iter().map(|n| (n.lat, n.long)).collect();
+        let mut stats = ChStats {
+            graph: GraphStats::collect(
+                &coordinates,
+                edges.iter().map(|e| (e.source, e.dest, e.length)),
+            ),
+            shortcuts: 0,
+            max_level: nodes.iter().map(|n| n.level).max().unwrap_or(0),
+            missing_children: 0,
+            disconnected_children: 0,
+            length_mismatches: 0,
+            level_violations: 0,
+        };
+        // shortcuts may run next to an edge between the same nodes, only
+        // parallel original edges are a problem
+        let mut pairs: Vec<(NodeId, NodeId)> = edges
+            .iter()
+            .filter(|e| e.edge_a.is_none() && e.edge_b.is_none())
+            .map(|e| (e.source, e.dest))
+            .collect();
+        pairs.sort();
+        stats.graph.parallel_edges = pairs.windows(2).filter(|w| w[0] == w[1]).count();
+
+        for edge in edges {
+            let (a, b) = match (edge.edge_a, edge.edge_b) {
+                (None, None) => continue,
+                (Some(a), Some(b)) if a < edges.len() && b < edges.len() => (&edges[a], &edges[b]),
+                _ => {
+                    stats.shortcuts += 1;
+                    stats.missing_children += 1;
+                    continue;
+                }
+            };
+            stats.shortcuts += 1;
+            if a.length + b.length != edge.length {
+                stats.length_mismatches += 1;
+            }
+            // children may be listed in either order
+            let via = if a.source == edge.source && a.dest == b.source && b.dest == edge.dest {
+                a.dest
+            } else if b.source == edge.source && b.dest == a.source && a.dest == edge.dest {
+                b.dest
+            } else {
+                stats.disconnected_children += 1;
+                continue;
+            };
+            let level = |id: NodeId| nodes.get(id).map(|n| n.level);
+            match (level(via), level(edge.source), level(edge.dest)) {
+                (Some(via), Some(source), Some(dest)) if via < source && via < dest => (),
+                _ => stats.level_violations += 1,
+            }
+        }
+        stats
+    }
+
+    pub fn problems(&self) -> Vec<String> {
+        let checks = [
+            (self.missing_children, "shortcuts with missing child edges"),
+            (self.disconnected_children, "shortcuts whose children don't connect"),
+            (self.length_mismatches, "shortcuts not as long as their children"),
+            (self.level_violations, "shortcuts bypassing a node of higher level"),
+        ];
+        let mut problems = self.graph.problems();
+        problems.extend(
+            checks
+                .iter()
+                .filter(|&&(count, _)| count > 0)
+                .map(|&(count, what)| format!("{} {}", count, what)),
+        );
+        problems
+    }
+}
+
+impl fmt::Display for ChStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.graph)?;
+        writeln!(f, "shortcuts:      {}", self.shortcuts)?;
+        writeln!(f, "max level:      {}", self.max_level)?;
+        for problem in &self.problems()[self.graph.problems().len()..] {
+            writeln!(f, "problem:        {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl ChGraph {
+    pub fn stats(&self) -> ChStats {
+        let edges: Vec<ChEdgeInfo> = (0..self.edge_count())
+            .map(|id| {
+                let (source, dest, length) = self.edge(id);
+                let (edge_a, edge_b) = match self.shortcuts[id] {
+                    Some((a, b)) => (Some(a), Some(b)),
+                    None => (None, None),
+                };
+                ChEdgeInfo::new(source, dest, length, 0, 0, edge_a, edge_b)
+            })
+            .collect();
+        ChStats::collect(&self.node_info, &edges)
+    }
+
+    /// The statistics of the hierarchy, or the problems found in it.
+    pub fn validate(&self) -> Result<ChStats, Vec<String>> {
+        let stats = self.stats();
+        let problems = stats.problems();
+        if problems.is_empty() {
+            Ok(stats)
+        } else {
+            Err(problems)
+        }
+    }
+}
+
+/// Statistics of a `.ch` file, collected without building the graph.
+pub fn check_ch_file<P: AsRef<Path>>(file: P) -> ChStats {
+    let (nodes, edges) = load_file(file);
+    ChStats::collect(&nodes, &edges)
+}
+
+#[test]
+fn ch_stats() {
+    // path 0 - 1 - 2 where 1 is contracted first
+    let nodes = vec![
+        ChNodeInfo::new(0, 10, 48.0, 9.0, 0, 1),
+        ChNodeInfo::new(1, 11, 48.1, 9.1, 0, 0),
+        ChNodeInfo::new(2, 12, 48.2, 9.2, 0, 2),
+    ];
+    let edges = || {
+        vec![
+            ChEdgeInfo::new(0, 1, 3, 12, 30, None, None),
+            ChEdgeInfo::new(1, 2, 4, 12, 30, None, None),
+            ChEdgeInfo::new(0, 2, 7, 0, 0, Some(0), Some(1)),
+            ChEdgeInfo::new(2, 1, 4, 12, 30, None, None),
+            ChEdgeInfo::new(1, 0, 3, 12, 30, None, None),
+            ChEdgeInfo::new(2, 0, 7, 0, 0, Some(4), Some(3)),
+        ]
+    };
+    let g = ChGraph::new(nodes, edges());
+    let stats = g.validate().expect("hierarchy is fine");
+    assert_eq!(stats.shortcuts, 2);
+    assert_eq!(stats.max_level, 2);
+    assert_eq!(stats.graph.edge_count, 6);
+
+    let nodes = vec![
+        ChNodeInfo::new(0, 10, 48.0, 9.0, 0, 1),
+        ChNodeInfo::new(1, 11, 48.1, 9.1, 0, 3),
+        ChNodeInfo::new(2, 12, 48.2, 9.2, 0, 2),
+    ];
+    let mut edges = edges();
+    edges[2].length = 8;
+    edges[5].edge_b = Some(9);
+    edges.push(ChEdgeInfo::new(1, 1, 7, 0, 0, Some(0), Some(1)));
+    let stats = ChStats::collect(&nodes, &edges);
+    assert_eq!(stats.shortcuts, 3);
+    assert_eq!(stats.missing_children, 1);
+    assert_eq!(stats.disconnected_children, 1);
+    assert_eq!(stats.length_mismatches, 1);
+    assert_eq!(stats.level_violations, 1);
+    assert_eq!(stats.graph.self_loops, 1);
+    assert_eq!(stats.problems().len(), 5);
+}
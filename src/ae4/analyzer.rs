@@ -0,0 +1,91 @@
+use std::collections::HashSet;
+
+use porter_stemmer::stem;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Stop words of `Analyzer::english`, the list Lucene uses.
+pub const ENGLISH_STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "if", "in", "into", "is",
+    "it", "no", "not", "of", "on", "or", "such", "that", "the", "their", "then", "there",
+    "these", "they", "this", "to", "was", "will", "with",
+];
+
+/// Turns text into index terms. The text is split at Unicode word
+/// boundaries, punctuation inside words is stripped and the words are lower
+/// cased. Then stop words are dropped, the rest is stemmed if `stemming` is
+/// set, and with `ngram` every term longer than n characters is replaced by
+/// its character n-grams. Documents and queries must use the same analyzer.
+#[derive(Debug, Clone, PartialEq, HeapSizeOf)]
+pub struct Analyzer {
+    pub stop_words: HashSet<String>,
+    pub stemming: bool,
+    pub ngram: Option<usize>,
+}
+
+impl Default for Analyzer {
+    /// Stemming only, like the movie descriptions have always been parsed.
+    fn default() -> Analyzer {
+        Analyzer {
+            stop_words: HashSet::new(),
+            stemming: true,
+            ngram: None,
+        }
+    }
+}
+
+impl Analyzer {
+    pub fn english() -> Analyzer {
+        Analyzer {
+            stop_words: ENGLISH_STOP_WORDS.iter().map(|w| (*w).to_owned()).collect(),
+            ..Analyzer::default()
+        }
+    }
+
+    /// A single word lower cased and without punctuation, but not stemmed,
+    /// as needed for prefixes.
+    pub fn normalize(&self, word: &str) -> String {
+        word.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(|c| c.to_lowercase())
+            .collect()
+    }
+
+    pub fn analyze(&self, text: &str) -> Vec<String> {
+        let mut terms = Vec::new();
+        for word in text.unicode_words() {
+            let word = self.normalize(word);
+            if word.is_empty() || self.stop_words.contains(&word) {
+                continue;
+            }
+            let term = if self.stemming { stem(&word) } else { word };
+            match self.ngram {
+                Some(n) if n > 0 && term.chars().count() > n => {
+                    let chars: Vec<char> = term.chars().collect();
+                    terms.extend(chars.windows(n).map(|w| w.iter().collect::<String>()));
+                }
+                _ => terms.push(term),
+            }
+        }
+        terms
+    }
+}
+
+#[test]
+fn analyze() {
+    let text = "The Wars, of the worlds: don't panic! Über-cool";
+    assert_eq!(
+        Analyzer::default().analyze(text),
+        vec!["the", "war", "of", "the", "world", "dont", "panic", "über", "cool"]
+    );
+    assert_eq!(
+        Analyzer::english().analyze(text),
+        vec!["war", "world", "dont", "panic", "über", "cool"]
+    );
+    let trigrams = Analyzer {
+        stemming: false,
+        ngram: Some(3),
+        ..Analyzer::english()
+    };
+    assert_eq!(trigrams.analyze("the Wars"), vec!["war", "ars"]);
+    assert_eq!(trigrams.analyze("of it"), Vec::<String>::new());
+}
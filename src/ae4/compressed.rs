@@ -0,0 +1,465 @@
+use ae4::*;
+use ae1::{read_varint, write_varint};
+
+/// Number of entries between two skip pointers.
+const SKIP_INTERVAL: usize = 64;
+
+/// Sorted document ids stored as varint encoded differences, usually one or
+/// two bytes per entry instead of eight. For the first entry of every block
+/// of `SKIP_INTERVAL` entries a skip pointer holds its id and the offset of
+/// the following entry, so cursors can jump over whole blocks.
+#[derive(Debug, Clone, Default, PartialEq, HeapSizeOf)]
+pub struct CompressedPostings {
+    bytes: Vec<u8>,
+    len: usize,
+    last: usize,
+    skips: Vec<(usize, usize)>,
+}
+
+impl CompressedPostings {
+    #[cfg(test)]
+    pub fn new(docs: &[usize]) -> CompressedPostings {
+        let mut list = CompressedPostings {
+            bytes: Vec::with_capacity(docs.len() * 2),
+            len: 0,
+            last: 0,
+            skips: Vec::with_capacity(docs.len() / SKIP_INTERVAL + 1),
+        };
+        for &doc in docs {
+            list.push(doc);
+        }
+        list
+    }
+
+    /// Appends an id larger than all ids of the list.
+    pub fn push(&mut self, doc: usize) {
+        debug_assert!(self.len == 0 || doc > self.last, "posting list not sorted");
+        write_varint(&mut self.bytes, (doc - self.last) as u64);
+        if self.len.is_multiple_of(SKIP_INTERVAL) {
+            self.skips.push((doc, self.bytes.len()));
+        }
+        self.len += 1;
+        self.last = doc;
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.bytes.shrink_to_fit();
+        self.skips.shrink_to_fit();
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn cursor(&self) -> PostingCursor<'_> {
+        PostingCursor {
+            list: self,
+            position: 0,
+            decoded: 0,
+            doc: 0,
+        }
+    }
+
+    pub fn decode(&self) -> Vec<usize> {
+        self.cursor().collect()
+    }
+}
+
+/// Iterates over a compressed list, `advance_to` uses the skip pointers.
+pub struct PostingCursor<'a> {
+    list: &'a CompressedPostings,
+    position: usize,
+    decoded: usize,
+    doc: usize,
+}
+
+impl<'a> PostingCursor<'a> {
+    /// The first id at least `target` which is the last decoded one or comes
+    /// after it.
+    pub fn advance_to(&mut self, target: usize) -> Option<usize> {
+        if self.decoded > 0 && self.doc >= target {
+            return Some(self.doc);
+        }
+        let skips = &self.list.skips;
+        let block = skips.partition_point(|&(doc, _)| doc <= target);
+        if block > 0 && (block - 1) * SKIP_INTERVAL >= self.decoded {
+            let (doc, position) = skips[block - 1];
+            self.doc = doc;
+            self.position = position;
+            self.decoded = (block - 1) * SKIP_INTERVAL + 1;
+        }
+        while self.decoded == 0 || self.doc < target {
+            self.next()?;
+        }
+        Some(self.doc)
+    }
+}
+
+impl<'a> Iterator for PostingCursor<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.decoded == self.list.len {
+            return None;
+        }
+        self.doc += read_varint(&self.list.bytes, &mut self.position) as usize;
+        self.decoded += 1;
+        Some(self.doc)
+    }
+}
+
+/// Intersection of two compressed lists, the shorter one is decoded and the
+/// longer one is only searched with its skip pointers.
+#[cfg(test)]
+pub fn intersect_compressed(list_a: &CompressedPostings, list_b: &CompressedPostings) -> Vec<usize> {
+    let (small, large) = if list_a.len() <= list_b.len() {
+        (list_a, list_b)
+    } else {
+        (list_b, list_a)
+    };
+    if small.is_empty() {
+        return Vec::new();
+    }
+    let mut cursor = large.cursor();
+    let mut result = Vec::new();
+    for doc in small.cursor() {
+        match cursor.advance_to(doc) {
+            Some(found) if found == doc => result.push(doc),
+            Some(_) => (),
+            None => break,
+        }
+    }
+    result
+}
+
+/// Ascending document ids which can be skipped through with `advance_to`,
+/// taken from a compressed list, from already evaluated ids or from the
+/// union of other cursors.
+pub enum DocCursor<'a> {
+    Compressed(PostingCursor<'a>),
+    /// Sorted ids and the position of the current one.
+    Slice(&'a [usize], usize),
+    Union(Vec<DocCursor<'a>>),
+}
+
+impl<'a> DocCursor<'a> {
+    /// Upper bound of the number of ids.
+    pub fn max_len(&self) -> usize {
+        match *self {
+            DocCursor::Compressed(ref cursor) => cursor.list.len(),
+            DocCursor::Slice(docs, _) => docs.len(),
+            DocCursor::Union(ref cursors) => cursors.iter().map(|c| c.max_len()).sum(),
+        }
+    }
+
+    /// The first id at least `target`, like `PostingCursor::advance_to`.
+    /// Slices are searched exponentially from the current position.
+    pub fn advance_to(&mut self, target: usize) -> Option<usize> {
+        match *self {
+            DocCursor::Compressed(ref mut cursor) => cursor.advance_to(target),
+            DocCursor::Slice(docs, ref mut position) => {
+                let mut bound = 1;
+                while *position + bound < docs.len() && docs[*position + bound] < target {
+                    bound *= 2;
+                }
+                let end = (*position + bound + 1).min(docs.len());
+                *position += docs[*position..end].partition_point(|&doc| doc < target);
+                docs.get(*position).cloned()
+            }
+            DocCursor::Union(ref mut cursors) => {
+                cursors.iter_mut().filter_map(|c| c.advance_to(target)).min()
+            }
+        }
+    }
+}
+
+/// Ids contained in all cursors. Every candidate of the shortest cursor is
+/// looked up in the others with `advance_to`, and an id found beyond it
+/// becomes the next target, so long lists are mostly skipped.
+pub fn intersect_cursors(mut cursors: Vec<DocCursor>) -> Vec<usize> {
+    cursors.sort_by_key(|c| c.max_len());
+    let mut result = Vec::new();
+    if cursors.is_empty() {
+        return result;
+    }
+    let mut target = 0;
+    'search: while let Some(candidate) = cursors[0].advance_to(target) {
+        for cursor in &mut cursors[1..] {
+            match cursor.advance_to(candidate) {
+                Some(doc) if doc == candidate => (),
+                Some(doc) => {
+                    target = doc;
+                    continue 'search;
+                }
+                None => break 'search,
+            }
+        }
+        result.push(candidate);
+        target = candidate + 1;
+    }
+    result
+}
+
+/// Posting list of a term: the document ids as `CompressedPostings` and for
+/// every entry the frequency, followed by the position differences in a
+/// positional index, as varints in `payload`. `payload_skips` holds the
+/// payload offset of the first entry of every skip block.
+#[derive(Debug, Clone, PartialEq, HeapSizeOf)]
+pub struct PostingList {
+    docs: CompressedPostings,
+    payload: Vec<u8>,
+    payload_skips: Vec<usize>,
+    positional: bool,
+}
+
+impl PostingList {
+    pub fn new(positional: bool) -> PostingList {
+        PostingList {
+            docs: CompressedPostings::default(),
+            payload: Vec::new(),
+            payload_skips: Vec::new(),
+            positional,
+        }
+    }
+
+    /// Appends the occurrences in `doc`, which must be larger than all
+    /// documents of the list. `positions` are ignored unless the list is
+    /// positional.
+    pub fn push(&mut self, doc: usize, freq: u32, positions: &[u32]) {
+        if self.docs.len().is_multiple_of(SKIP_INTERVAL) {
+            self.payload_skips.push(self.payload.len());
+        }
+        self.docs.push(doc);
+        write_varint(&mut self.payload, u64::from(freq));
+        if self.positional {
+            debug_assert_eq!(positions.len(), freq as usize);
+            let mut last = 0;
+            for &position in positions {
+                write_varint(&mut self.payload, u64::from(position - last));
+                last = position;
+            }
+        }
+    }
+
+    /// Frees the spare capacity left from pushing.
+    pub fn shrink_to_fit(&mut self) {
+        self.docs.shrink_to_fit();
+        self.payload.shrink_to_fit();
+        self.payload_skips.shrink_to_fit();
+    }
+
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+
+    pub fn docs(&self) -> &CompressedPostings {
+        &self.docs
+    }
+
+    pub fn cursor(&self) -> PostingListCursor<'_> {
+        PostingListCursor {
+            list: self,
+            docs: self.docs.cursor(),
+            payload: 0,
+            payload_entry: 0,
+        }
+    }
+
+    /// All entries decoded.
+    pub fn iter(&self) -> impl Iterator<Item = Posting> + '_ {
+        let mut cursor = self.cursor();
+        ::std::iter::from_fn(move || {
+            cursor.next()?;
+            Some(cursor.posting())
+        })
+    }
+}
+
+/// Iterates over the documents of a `PostingList` like `PostingCursor`. The
+/// payload of the current document is only decoded when it is asked for.
+pub struct PostingListCursor<'a> {
+    list: &'a PostingList,
+    docs: PostingCursor<'a>,
+    payload: usize,
+    payload_entry: usize,
+}
+
+impl<'a> PostingListCursor<'a> {
+    pub fn advance_to(&mut self, target: usize) -> Option<usize> {
+        self.docs.advance_to(target)
+    }
+
+    /// Moves `payload` to the payload of the current entry, jumping over
+    /// whole blocks like the document ids do.
+    fn seek_payload(&mut self) -> usize {
+        let entry = self.docs.decoded.checked_sub(1).expect("cursor before the first entry");
+        let block = entry / SKIP_INTERVAL;
+        if block * SKIP_INTERVAL > self.payload_entry {
+            self.payload = self.list.payload_skips[block];
+            self.payload_entry = block * SKIP_INTERVAL;
+        }
+        let bytes = &self.list.payload;
+        while self.payload_entry < entry {
+            let freq = read_varint(bytes, &mut self.payload);
+            if self.list.positional {
+                for _ in 0..freq {
+                    read_varint(bytes, &mut self.payload);
+                }
+            }
+            self.payload_entry += 1;
+        }
+        self.payload
+    }
+
+    /// Frequency of the term in the current document.
+    pub fn freq(&mut self) -> u32 {
+        let mut position = self.seek_payload();
+        read_varint(&self.list.payload, &mut position) as u32
+    }
+
+    /// The current entry decoded.
+    pub fn posting(&mut self) -> Posting {
+        let mut offset = self.seek_payload();
+        let bytes = &self.list.payload;
+        let freq = read_varint(bytes, &mut offset) as u32;
+        let mut positions = Vec::new();
+        if self.list.positional {
+            let mut position = 0;
+            for _ in 0..freq {
+                position += read_varint(bytes, &mut offset) as u32;
+                positions.push(position);
+            }
+        }
+        Posting {
+            doc: self.docs.doc,
+            freq,
+            positions,
+        }
+    }
+}
+
+impl<'a> Iterator for PostingListCursor<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        self.docs.next()
+    }
+}
+
+impl FieldIndex {
+    /// Ids of the movies containing all `terms`, intersected on the
+    /// compressed lists.
+    pub fn and_query(&self, terms: &[&str]) -> Vec<usize> {
+        let mut cursors = Vec::with_capacity(terms.len());
+        for term in terms {
+            match self.get(term) {
+                Some(list) => cursors.push(DocCursor::Compressed(list.docs().cursor())),
+                None => return Vec::new(),
+            }
+        }
+        intersect_cursors(cursors)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ae4::*;
+    use super::*;
+    use test::Bencher;
+
+    fn skewed_lists() -> (Vec<usize>, Vec<usize>) {
+        let large = (0..1_000_000).filter(|i| i % 3 != 1).collect();
+        let small = (0..1_000_000).step_by(997).collect();
+        (small, large)
+    }
+
+    #[test]
+    fn compressed_intersection() {
+        let a: Vec<usize> = (0..5000).filter(|i| i % 3 == 0).collect();
+        let b: Vec<usize> = (0..5000).filter(|i| i % 7 == 2 || *i > 4900).collect();
+        let c: Vec<usize> = vec![0, 9, 100, 1002, 4902, 4998, 5000];
+        let (ca, cb, cc) = (
+            CompressedPostings::new(&a),
+            CompressedPostings::new(&b),
+            CompressedPostings::new(&c),
+        );
+        assert_eq!(ca.decode(), a);
+        assert_eq!(ca.len(), a.len());
+        assert!(CompressedPostings::new(&[]).is_empty());
+        assert_eq!(intersect_compressed(&ca, &cb), intersect(&a, &b));
+        assert_eq!(intersect_compressed(&cc, &ca), vec![0, 9, 1002, 4902, 4998]);
+
+        let mut cursor = ca.cursor();
+        assert_eq!(cursor.advance_to(1000), Some(1002));
+        assert_eq!(cursor.advance_to(1001), Some(1002));
+        assert_eq!(cursor.next(), Some(1005));
+        assert_eq!(cursor.advance_to(4999), None);
+
+        let movies: Vec<Movie> = [
+            "Star Wars\tA war in space between rebels",
+            "War Horse\tA horse in the war",
+            "Space War\tThe space war of the planets",
+        ].iter()
+            .map(|line| line.parse().unwrap())
+            .collect();
+        let index = build_inverted_index(&movies, true);
+        let index = index.field(Field::Description);
+        assert_eq!(index.and_query(&["war", "space", "the"]), vec![2]);
+        assert_eq!(index.and_query(&["war", "space"]), vec![0, 2]);
+        assert_eq!(index.and_query(&["war", "pasta"]), Vec::<usize>::new());
+
+        // payloads are found after skipping whole blocks
+        let mut list = PostingList::new(true);
+        for doc in 0..1000 {
+            let positions: Vec<u32> = (0..doc as u32 % 4).map(|p| p * 3).collect();
+            list.push(doc * 2, positions.len() as u32, &positions);
+        }
+        let mut cursor = list.cursor();
+        assert_eq!(cursor.advance_to(1501), Some(1502));
+        assert_eq!(cursor.freq(), 3);
+        assert_eq!(cursor.posting().positions, vec![0, 3, 6]);
+        assert_eq!(cursor.next(), Some(1504));
+        assert_eq!(cursor.posting().freq, 0);
+        assert_eq!(list.iter().nth(7).unwrap().positions, vec![0, 3, 6]);
+
+        let mut either = DocCursor::Union(vec![
+            DocCursor::Slice(&c, 0),
+            DocCursor::Compressed(cb.cursor()),
+        ]);
+        assert_eq!(either.advance_to(4950), Some(4950));
+        assert_eq!(either.advance_to(5000), Some(5000));
+        let evaluated = intersect(&a, &union(&b, &c));
+        let cursors = vec![
+            DocCursor::Compressed(ca.cursor()),
+            DocCursor::Union(vec![DocCursor::Compressed(cb.cursor()), DocCursor::Slice(&c, 0)]),
+        ];
+        assert_eq!(intersect_cursors(cursors), evaluated);
+    }
+
+    #[bench]
+    fn bench_linear_intersect(b: &mut Bencher) {
+        let (small, large) = skewed_lists();
+        b.iter(|| intersect(&small, &large));
+    }
+
+    #[bench]
+    fn bench_galloping_intersect(b: &mut Bencher) {
+        let (small, large) = skewed_lists();
+        b.iter(|| galloping_intersect(&small, &large));
+    }
+
+    #[bench]
+    fn bench_compressed_intersect(b: &mut Bencher) {
+        let (small, large) = skewed_lists();
+        let (small, large) = (CompressedPostings::new(&small), CompressedPostings::new(&large));
+        b.iter(|| intersect_compressed(&small, &large));
+    }
+}
@@ -0,0 +1,100 @@
+use ae4::*;
+
+use std::ops::Range;
+
+/// Levenshtein distance of `a` and `b` in characters, `None` if it is larger
+/// than `max`. Stops as soon as a row of the table exceeds `max`.
+pub fn edit_distance(a: &str, b: &str, max: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    let mut row: Vec<usize> = (0..b.len() + 1).collect();
+    for (i, &ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        let mut best = row[0];
+        for (j, &cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            let value = (diagonal + cost).min(row[j] + 1).min(row[j + 1] + 1);
+            diagonal = row[j + 1];
+            row[j + 1] = value;
+            best = best.min(value);
+        }
+        if best > max {
+            return None;
+        }
+    }
+    Some(row[b.len()]).filter(|&d| d <= max)
+}
+
+/// Edit distance allowed when correcting an unknown term: none for up to two
+/// characters, one for up to five and two for longer terms.
+pub fn auto_distance(term: &str) -> usize {
+    match term.chars().count() {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    }
+}
+
+impl FieldIndex {
+    /// Ids of the terms starting with `prefix`, a range of the sorted
+    /// vocabulary.
+    pub fn prefix_terms(&self, prefix: &str) -> Range<usize> {
+        let start = self.vocabulary.partition_point(|t| t.as_str() < prefix);
+        let len = self.vocabulary[start..].partition_point(|t| t.starts_with(prefix));
+        start..start + len
+    }
+
+    /// Ids of the terms at most `max_distance` edits away from `term`,
+    /// closest first. Scans the whole vocabulary.
+    pub fn fuzzy_terms(&self, term: &str, max_distance: usize) -> Vec<usize> {
+        let mut found: Vec<(usize, usize)> = self.vocabulary
+            .iter()
+            .enumerate()
+            .filter_map(|(id, t)| edit_distance(term, t, max_distance).map(|d| (d, id)))
+            .collect();
+        found.sort();
+        found.into_iter().map(|(_, id)| id).collect()
+    }
+
+    /// Sorted ids of the movies containing any of the terms.
+    pub fn merged_postings<I: IntoIterator<Item = usize>>(&self, term_ids: I) -> Vec<usize> {
+        let mut docs: Vec<usize> = term_ids
+            .into_iter()
+            .flat_map(|id| self.postings[id].cursor())
+            .collect();
+        docs.sort_unstable();
+        docs.dedup();
+        docs
+    }
+}
+
+#[test]
+fn prefix_and_fuzzy_terms() {
+    assert_eq!(edit_distance("kitten", "sitting", 3), Some(3));
+    assert_eq!(edit_distance("kitten", "sitting", 2), None);
+    assert_eq!(edit_distance("war", "wars", 1), Some(1));
+    assert_eq!(edit_distance("", "ab", 2), Some(2));
+    assert_eq!(edit_distance("ab", "ba", 1), None);
+
+    let movies: Vec<Movie> = [
+        "Star Wars\tA war in space between stars and starships",
+        "Starman\tAn alien stranded on earth",
+        "Warm Bodies\tA warm zombie romance",
+    ].iter()
+        .map(|line| line.parse().unwrap())
+        .collect();
+    let index = build_inverted_index(&movies, false);
+    let index = index.field(Field::Description);
+    let names = |ids: Vec<usize>| -> Vec<String> {
+        ids.into_iter().map(|id| index.vocabulary[id].clone()).collect()
+    };
+    assert_eq!(names(index.prefix_terms("star").collect()), vec!["star", "starship"]);
+    assert_eq!(names(index.prefix_terms("stra").collect()), vec!["strand"]);
+    assert!(index.prefix_terms("zz").next().is_none());
+    assert_eq!(names(index.fuzzy_terms("wars", 1)), vec!["war", "warm"]);
+    assert_eq!(index.merged_postings(index.fuzzy_terms("wars", 1)), vec![0, 2]);
+}
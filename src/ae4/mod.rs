@@ -1,19 +1,148 @@
+use std::fmt;
 use std::str::FromStr;
 use std::path::Path;
 use std::convert::From;
 use std::collections::HashMap;
-use std::collections::BTreeSet;
+use std::cmp::Ordering;
 use std::time::{Instant, Duration};
 
-use porter_stemmer::stem;
 use heapsize::HeapSizeOf;
 
-type InvertedIndex<'a> = HashMap<&'a str, Vec<usize>>;
+mod rank;
+mod query;
+mod positional;
+mod storage;
+mod compressed;
+mod analyzer;
+mod fuzzy;
+mod segments;
+
+pub use self::rank::{Bm25, ranked_query, score_docs};
+pub use self::query::{parse_query, parse_query_with};
+pub use self::positional::proximity_query;
+pub use self::compressed::{DocCursor, PostingList, intersect_cursors};
+pub use self::analyzer::Analyzer;
+pub use self::fuzzy::auto_distance;
+pub use self::segments::SegmentedIndex;
+
+/// Occurrence of a term in a field of a movie, as decoded from a
+/// `PostingList`. `positions` holds the word offsets of all occurrences if
+/// the index is positional and is empty otherwise.
+#[derive(Debug, Clone, PartialEq, Eq, HeapSizeOf)]
+pub struct Posting {
+    pub doc: usize,
+    pub freq: u32,
+    pub positions: Vec<u32>,
+}
+
+/// The indexed parts of a movie. Queries search all fields unless they are
+/// scoped like `title:alien`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Field {
+    Title,
+    Description,
+}
+
+impl Field {
+    pub const ALL: [Field; 2] = [Field::Title, Field::Description];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::Title => "title",
+            Field::Description => "desc",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Field> {
+        match name {
+            "title" => Some(Field::Title),
+            "desc" | "description" => Some(Field::Description),
+            _ => None,
+        }
+    }
+}
+
+/// Entry of the document table.
+#[derive(Debug, Clone, PartialEq, Eq, HeapSizeOf)]
+pub struct Document {
+    pub title: String,
+}
+
+/// Terms of one field: `vocabulary` holds them in sorted order, which allows
+/// prefix lookups, and `postings` the compressed list of each term at the
+/// same position. `lengths` is the number of terms of the field in every
+/// document, as needed for ranking.
+#[derive(Debug, Default, PartialEq, HeapSizeOf)]
+pub struct FieldIndex {
+    pub vocabulary: Vec<String>,
+    pub postings: Vec<PostingList>,
+    pub lengths: Vec<u32>,
+}
+
+impl FieldIndex {
+    pub fn term_id(&self, term: &str) -> Option<usize> {
+        self.vocabulary.binary_search_by(|t| t.as_str().cmp(term)).ok()
+    }
+
+    pub fn get(&self, term: &str) -> Option<&PostingList> {
+        self.term_id(term).map(|id| &self.postings[id])
+    }
+
+    pub fn average_length(&self) -> f64 {
+        let total: u64 = self.lengths.iter().map(|&l| u64::from(l)).sum();
+        total as f64 / self.lengths.len().max(1) as f64
+    }
+}
+
+/// Index owning its data, so it can be saved and loaded independently of the
+/// movies, with one `FieldIndex` per `Field` in the order of `Field::ALL`.
+/// Queries are analyzed with the `analyzer` the fields were indexed with.
+#[derive(Debug, PartialEq, HeapSizeOf)]
+pub struct InvertedIndex {
+    pub fields: Vec<FieldIndex>,
+    pub documents: Vec<Document>,
+    pub positional: bool,
+    pub analyzer: Analyzer,
+}
 
+impl InvertedIndex {
+    pub fn field(&self, field: Field) -> &FieldIndex {
+        &self.fields[field as usize]
+    }
+
+    /// Posting list of a term in the descriptions.
+    pub fn get(&self, term: &str) -> Option<&PostingList> {
+        self.field(Field::Description).get(term)
+    }
+
+    /// Whether the term occurs in any field.
+    pub fn contains(&self, term: &str) -> bool {
+        self.fields.iter().any(|f| f.term_id(term).is_some())
+    }
+
+    pub fn doc_count(&self) -> usize {
+        self.documents.len()
+    }
+}
+
+/// Matches of a query. `ids` are ascending unless the query was ranked, then
+/// they are ordered like `scores`, best first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryResult {
+    pub ids: Vec<usize>,
+    pub scores: Option<Vec<f64>>,
+    /// Query terms which occur in no field.
+    pub unknown_terms: Vec<String>,
+    /// Unknown terms and the similar terms searched for instead.
+    pub corrections: Vec<(String, Vec<String>)>,
+    pub duration: Duration,
+}
+
+/// A movie with its description as given in `text`.
 #[derive(Debug, PartialEq, Eq, HeapSizeOf)]
 pub struct Movie {
     pub title: String,
-    pub desc: String,
+    pub text: String,
 }
 
 impl FromStr for Movie {
@@ -21,23 +150,25 @@ impl FromStr for Movie {
     fn from_str(s: &str) -> Result<Movie, Self::Err> {
         let mut split = s.split('\t');
         let title = split.next().ok_or(StrError { msg: "No title found" })?;
-        let desc_old = split
-            .next()
-            .ok_or(StrError { msg: "No description found" })?
-            .to_lowercase();
-        let mut desc = String::new();
-        for word in desc_old.split(' ') {
-            desc.push_str(stem(word).as_str());
-            desc.push(' ');
-        }
+        let text = split.next().ok_or(StrError { msg: "No description found" })?;
 
         Ok(Movie {
             title: title.to_owned(),
-            desc: desc.to_owned(),
+            text: text.to_owned(),
         })
 
     }
 }
+
+impl Movie {
+    /// Text of a field as given.
+    pub fn field(&self, field: Field) -> &str {
+        match field {
+            Field::Title => &self.title,
+            Field::Description => &self.text,
+        }
+    }
+}
 pub fn load_movies<P: AsRef<Path>>(file: P) -> Result<Vec<Movie>, StrError> {
     use std::fs::File;
     use std::io::Read;
@@ -68,18 +199,20 @@ pub fn load_movies<P: AsRef<Path>>(file: P) -> Result<Vec<Movie>, StrError> {
 }
 
 
-pub fn build_inverted_index(movies: &[Movie]) -> InvertedIndex {
-    let start = Instant::now();
-    let mut index = HashMap::new();
-    for (i, movie) in movies.iter().enumerate() {
-        for word in movie.desc.split(' ') {
-            let mut e = index.entry(word).or_insert_with(Vec::new);
-            if e.len() == 0 || e[e.len() - 1] != i {
-                e.push(i);
-            }
-        }
-    }
+/// Builds the index of the titles and descriptions with the default
+/// `Analyzer`, with `positions` the word offsets of every occurrence are
+/// recorded too, which allows phrase queries without scanning the texts.
+pub fn build_inverted_index(movies: &[Movie], positions: bool) -> InvertedIndex {
+    build_inverted_index_with(movies, positions, Analyzer::default())
+}
 
+pub fn build_inverted_index_with(
+    movies: &[Movie],
+    positions: bool,
+    analyzer: Analyzer,
+) -> InvertedIndex {
+    let start = Instant::now();
+    let index = index_movies(movies, positions, analyzer);
     println!(
         "index construction took: {:?}",
         Instant::now().duration_since(start)
@@ -89,41 +222,100 @@ pub fn build_inverted_index(movies: &[Movie]) -> InvertedIndex {
     index
 }
 
-pub fn query_index(index: &InvertedIndex, movies: &[Movie], query: &str) -> Duration {
-    let start = Instant::now();
-    let query = query.to_lowercase();
-    let mut lists = Vec::new();
-    for word in query.trim().split(' ') {
-        let stemmed = stem(word);
-        let list = match index.get(stemmed.as_str()) {
-            Some(list) => list,
-            None => continue,
-        };
-        lists.push(list);
-    }
-
-    if lists.is_empty() {
-        //println!("no results");
-        return Instant::now().duration_since(start);
-    }
-    let mut result = lists[0].clone();
-    if lists.len() > 1 {
-        for list in &lists[1..] {
-            result = intersect(&list, &result);
+/// Builds an index like `build_inverted_index_with` without printing the
+/// time it took, for small batches of movies.
+fn index_movies(movies: &[Movie], positions: bool, analyzer: Analyzer) -> InvertedIndex {
+    let mut index = InvertedIndex {
+        fields: Field::ALL.iter().map(|_| FieldIndex::default()).collect(),
+        documents: Vec::with_capacity(movies.len()),
+        positional: positions,
+        analyzer,
+    };
+    let mut ids: Vec<HashMap<String, usize>> = Field::ALL.iter().map(|_| HashMap::new()).collect();
+    for (i, movie) in movies.iter().enumerate() {
+        for &field in &Field::ALL {
+            let field_index = &mut index.fields[field as usize];
+            let ids = &mut ids[field as usize];
+            // offsets of every term of the field in this movie
+            let mut found: HashMap<usize, Vec<u32>> = HashMap::new();
+            let mut length = 0;
+            for word in index.analyzer.analyze(movie.field(field)) {
+                let id = match ids.get(&word) {
+                    Some(&id) => id,
+                    None => {
+                        ids.insert(word, field_index.postings.len());
+                        field_index.postings.push(PostingList::new(positions));
+                        field_index.postings.len() - 1
+                    }
+                };
+                found.entry(id).or_default().push(length);
+                length += 1;
+            }
+            for (id, offsets) in found {
+                field_index.postings[id].push(i, offsets.len() as u32, &offsets);
+            }
+            field_index.lengths.push(length);
         }
+        index.documents.push(Document { title: movie.title.clone() });
     }
-    let finish = Instant::now();
 
-    let count = result.len();
-    for i in result {
-        println!("{}: {}", i, movies[i].title);
+    for (field_index, ids) in index.fields.iter_mut().zip(ids) {
+        let mut terms: Vec<(String, usize)> = ids.into_iter().collect();
+        terms.sort();
+        let mut postings = ::std::mem::take(&mut field_index.postings);
+        for (term, id) in terms {
+            let mut list = ::std::mem::replace(&mut postings[id], PostingList::new(positions));
+            list.shrink_to_fit();
+            field_index.vocabulary.push(term);
+            field_index.postings.push(list);
+        }
     }
-    println!("{} results", count);
-
-    println!("");
-
-    finish.duration_since(start)
+    index
+}
 
+/// Evaluates a boolean query, see `parse_query`, after expanding prefixes,
+/// fuzzy and unknown terms, see `Query::expand`. With `ranking` the matches
+/// are ordered by their BM25 score for the terms which are not negated.
+pub fn query_index(
+    index: &InvertedIndex,
+    movies: &[Movie],
+    query: &str,
+    ranking: Option<&Bm25>,
+) -> Result<QueryResult, StrError> {
+    let start = Instant::now();
+    let parsed = parse_query_with(query, &index.analyzer)?;
+    if !index.positional && movies.len() < index.documents.len() && parsed.has_phrase() {
+        return Err(StrError { msg: "Phrase queries need a positional index or the movies" });
+    }
+    let unknown_terms = parsed
+        .terms(true)
+        .into_iter()
+        .filter(|term| !index.contains(term))
+        .map(|term| term.to_owned())
+        .collect();
+    let mut corrections = Vec::new();
+    let query = parsed.expand(index, &mut corrections);
+    let mut ids = query.evaluate(index, movies);
+    let scores = match ranking {
+        Some(params) => {
+            let scores = score_docs(index, &ids, &query.terms(false), params);
+            let mut ranked: Vec<(usize, f64)> = ids.into_iter().zip(scores).collect();
+            ranked.sort_by(|a, b| {
+                b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal).then(a.0.cmp(&b.0))
+            });
+            ids = ranked.iter().map(|&(id, _)| id).collect();
+            Some(ranked.into_iter().map(|(_, score)| score).collect())
+        }
+        None => None,
+    };
+
+    Ok(QueryResult {
+        ids,
+        scores,
+        unknown_terms,
+        corrections,
+        duration: Instant::now().duration_since(start),
+    })
 }
 
 pub fn intersect(list_a: &[usize], list_b: &[usize]) -> Vec<usize> {
@@ -146,28 +338,103 @@ pub fn intersect(list_a: &[usize], list_b: &[usize]) -> Vec<usize> {
     result
 }
 
-pub fn naive_query(movies: &[Movie], query: &str) -> Duration {
-    let start = Instant::now();
-    let mut result = BTreeSet::new();
-    let mut query = stem(query.to_lowercase().trim());
-    query.insert(0, ' ');
-    query.push(' ');
+/// Intersection of two sorted lists, the entries of the shorter list are
+/// looked up in the longer one by exponential search. Much faster than
+/// `intersect` if the lengths differ a lot.
+pub fn galloping_intersect(list_a: &[usize], list_b: &[usize]) -> Vec<usize> {
+    let (small, large) = if list_a.len() <= list_b.len() {
+        (list_a, list_b)
+    } else {
+        (list_b, list_a)
+    };
+    let mut result = Vec::with_capacity(small.len());
+    let mut low = 0;
+    for &id in small {
+        if low >= large.len() {
+            break;
+        }
+        let mut bound = 1;
+        while low + bound < large.len() && large[low + bound] < id {
+            bound *= 2;
+        }
+        let end = (low + bound + 1).min(large.len());
+        match large[low..end].binary_search(&id) {
+            Ok(i) => {
+                result.push(id);
+                low += i + 1;
+            }
+            Err(i) => low += i,
+        }
+    }
+    result
+}
 
-    for (i, movie) in movies.iter().enumerate() {
-        if movie.desc.contains(query.as_str()) {
-            result.insert(i);
+/// Intersection of several sorted lists with `intersect_cursors`, the
+/// candidates of the shortest list are searched exponentially in the others.
+pub fn intersect_all<L: AsRef<[usize]>>(lists: &[L]) -> Vec<usize> {
+    intersect_cursors(lists.iter().map(|l| DocCursor::Slice(l.as_ref(), 0)).collect())
+}
+
+/// Ids contained in `list_a` or `list_b`, both sorted.
+pub fn union(list_a: &[usize], list_b: &[usize]) -> Vec<usize> {
+    let mut result = Vec::with_capacity(list_a.len() + list_b.len());
+    let (mut i, mut j) = (0, 0);
+    while i < list_a.len() && j < list_b.len() {
+        if list_a[i] < list_b[j] {
+            result.push(list_a[i]);
+            i += 1;
+        } else if list_a[i] > list_b[j] {
+            result.push(list_b[j]);
+            j += 1;
+        } else {
+            result.push(list_a[i]);
+            i += 1;
+            j += 1;
         }
     }
-    let finish = Instant::now();
+    result.extend_from_slice(&list_a[i..]);
+    result.extend_from_slice(&list_b[j..]);
+    result
+}
+
+/// Ids of the sorted `list_a` which are not in the sorted `list_b`.
+pub fn difference(list_a: &[usize], list_b: &[usize]) -> Vec<usize> {
+    let mut result = Vec::with_capacity(list_a.len());
+    let mut j = 0;
+    for &id in list_a {
+        while j < list_b.len() && list_b[j] < id {
+            j += 1;
+        }
+        if j == list_b.len() || list_b[j] != id {
+            result.push(id);
+        }
+    }
+    result
+}
 
-    let count = result.len();
-    for i in result {
-        println!("{}: {}", i, movies[i].title)
+/// Linear scan over all descriptions for the query terms in a row, both
+/// analyzed with `analyzer`, which should be the one of the index compared
+/// against. `unknown_terms` and `corrections` are always empty.
+pub fn naive_query(movies: &[Movie], query: &str, analyzer: &Analyzer) -> QueryResult {
+    let start = Instant::now();
+    let query = analyzer.analyze(query);
+    let mut ids = Vec::new();
+    if !query.is_empty() {
+        for (i, movie) in movies.iter().enumerate() {
+            let words = analyzer.analyze(&movie.text);
+            if words.windows(query.len()).any(|w| w == query.as_slice()) {
+                ids.push(i);
+            }
+        }
     }
-    println!("{} results", count);
 
-    println!("");
-    finish.duration_since(start)
+    QueryResult {
+        ids,
+        scores: None,
+        unknown_terms: Vec::new(),
+        corrections: Vec::new(),
+        duration: Instant::now().duration_since(start),
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -175,6 +442,12 @@ pub struct StrError {
     msg: &'static str,
 }
 
+impl fmt::Display for StrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.msg)
+    }
+}
+
 impl From<::std::io::Error> for StrError {
     fn from(_: ::std::io::Error) -> Self {
         StrError { msg: "io error" }
@@ -191,13 +464,49 @@ mod test {
         assert_eq!(
             Ok(super::Movie {
                 title: "test title".to_owned(),
-                desc: "some movi titl ".to_owned(),
+                text: "Some movie title".to_owned(),
             }),
             ::std::str::FromStr::from_str("test title\tSome movie title")
         );
 
     }
 
+    #[test]
+    fn naive_query_uses_analyzer() {
+        use ae4::*;
+        let movies: Vec<Movie> = ["Star Wars\tThe war of the stars", "Cooking\tWar on pasta"]
+            .iter()
+            .map(|line| line.parse().unwrap())
+            .collect();
+        let english = Analyzer::english();
+        let index = build_inverted_index_with(&movies, true, english.clone());
+        let run = |query: &str, analyzer: &Analyzer| naive_query(&movies, query, analyzer).ids;
+        assert_eq!(run("the war", &english), vec![0, 1]);
+        let indexed = query_index(&index, &movies, "\"the war\"", None).unwrap();
+        assert_eq!(run("the war", &english), indexed.ids);
+        assert_eq!(run("the war", &Analyzer::default()), vec![0]);
+        assert_eq!(run("war stars", &english), vec![0]);
+        assert!(run("the", &english).is_empty());
+    }
+
+    #[test]
+    fn union_difference_test() {
+        assert_eq!(vec![1, 2, 3, 4, 6, 8], super::union(&[1, 2, 3, 4], &[2, 3, 6, 8]));
+        assert_eq!(vec![1, 4], super::difference(&[1, 2, 3, 4], &[2, 3, 6, 8]));
+        assert_eq!(vec![2, 3], super::union(&[], &[2, 3]));
+    }
+
+    #[test]
+    fn galloping_test() {
+        let a: Vec<usize> = (0..1000).filter(|i| i % 3 == 0).collect();
+        let b: Vec<usize> = (0..1000).filter(|i| i % 7 == 2).collect();
+        let c = vec![2, 9, 23, 51, 996, 999];
+        assert_eq!(super::galloping_intersect(&a, &b), super::intersect(&a, &b));
+        assert_eq!(super::galloping_intersect(&c, &a), vec![9, 51, 996, 999]);
+        assert_eq!(super::intersect_all(&[&a, &b, &c]), vec![9, 51, 996]);
+        assert_eq!(super::intersect_all(&[a.clone(), Vec::new()]), Vec::<usize>::new());
+    }
+
     #[test]
     fn intersect_test() {
         assert_eq!(
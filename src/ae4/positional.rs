@@ -0,0 +1,123 @@
+use ae4::*;
+
+/// Offsets of `term` in the analyzed description `words`.
+fn positions_in(words: &[String], term: &str) -> Vec<u32> {
+    words
+        .iter()
+        .enumerate()
+        .filter(|&(_, w)| w == term)
+        .map(|(i, _)| i as u32)
+        .collect()
+}
+
+/// Whether one offset from every list can be picked so that they ascend with
+/// at most `slop` other words between neighbours. All lists must be sorted.
+fn within<P: AsRef<[u32]>>(positions: &[P], slop: u32) -> bool {
+    let mut reached: Vec<u32> = match positions.first() {
+        Some(first) => first.as_ref().to_vec(),
+        None => return false,
+    };
+    for next in &positions[1..] {
+        let mut step = Vec::new();
+        let mut j = 0;
+        for &q in next.as_ref() {
+            while j < reached.len() && reached[j].saturating_add(slop).saturating_add(1) < q {
+                j += 1;
+            }
+            if j < reached.len() && reached[j] < q {
+                step.push(q);
+            }
+        }
+        if step.is_empty() {
+            return false;
+        }
+        reached = step;
+    }
+    true
+}
+
+/// Ids of the movies containing `terms` in `field` in the given order with
+/// at most `slop` other words between neighbouring terms, a slop of 0 is an
+/// exact phrase. The positions come from the postings of a positional index,
+/// otherwise only the texts of the candidates are scanned.
+pub fn proximity_query(
+    index: &InvertedIndex,
+    movies: &[Movie],
+    field: Field,
+    terms: &[String],
+    slop: u32,
+) -> Vec<usize> {
+    let mut lists = Vec::with_capacity(terms.len());
+    for term in terms {
+        match index.field(field).get(term) {
+            Some(list) => lists.push(list),
+            None => return Vec::new(),
+        }
+    }
+    let mut candidates =
+        intersect_cursors(lists.iter().map(|l| DocCursor::Compressed(l.docs().cursor())).collect());
+
+    let mut cursors: Vec<_> = lists.iter().map(|l| l.cursor()).collect();
+    candidates.retain(|&doc| {
+        let positions: Vec<Vec<u32>> = if index.positional {
+            cursors
+                .iter_mut()
+                .map(|cursor| {
+                    cursor.advance_to(doc);
+                    cursor.posting().positions
+                })
+                .collect()
+        } else {
+            let words = index.analyzer.analyze(movies[doc].field(field));
+            terms.iter().map(|term| positions_in(&words, term)).collect()
+        };
+        within(&positions, slop)
+    });
+    candidates
+}
+
+#[test]
+fn phrase_and_proximity() {
+    let movies: Vec<Movie> = [
+        "Star Wars\tA war in space between rebels",
+        "Space War\tThe space war of the planets",
+        "Long War\tWar in deep space and war again",
+        "Repeat\tWait and wait and wait for space",
+    ].iter()
+        .map(|line| line.parse().unwrap())
+        .collect();
+    let terms = |phrase: &str| -> Vec<String> {
+        phrase.split(' ').map(|w| w.to_owned()).collect()
+    };
+
+    let positional = build_inverted_index(&movies, true);
+    let plain = build_inverted_index(&movies, false);
+    let third = |list: &PostingList| list.iter().nth(2).unwrap().positions;
+    assert_eq!(third(positional.get("war").unwrap()), vec![0, 5]);
+    assert_eq!(third(positional.field(Field::Title).get("war").unwrap()), vec![1]);
+    assert!(third(plain.get("war").unwrap()).is_empty());
+    assert_eq!(query_index(&positional, &[], "\"space war\"", None).unwrap().ids, vec![1]);
+    assert!(query_index(&plain, &[], "\"space war\"", None).is_err());
+    assert_eq!(query_index(&plain, &[], "space war", None).unwrap().ids, vec![0, 1, 2]);
+    let anywhere = query_index(&positional, &[], "\"space war\"~4294967295", None);
+    assert_eq!(anywhere.unwrap().ids, vec![1, 2]);
+    assert!(query_index(&positional, &[], "\"space war\"~4294967296", None).is_err());
+
+    for index in &[positional, plain] {
+        let run = |phrase: &str, slop| {
+            proximity_query(index, &movies, Field::Description, &terms(phrase), slop)
+        };
+        assert_eq!(run("space war", 0), vec![1]);
+        assert_eq!(run("war space", 0), Vec::<usize>::new());
+        assert_eq!(run("war space", 1), vec![0]);
+        assert_eq!(run("war space", 2), vec![0, 2]);
+        assert_eq!(run("wait and wait", 0), vec![3]);
+        assert_eq!(run("wait space", 1), vec![3]);
+        assert_eq!(run("space rocket", 5), Vec::<usize>::new());
+        assert_eq!(run("space war", u32::MAX), vec![1, 2]);
+        assert_eq!(
+            proximity_query(index, &movies, Field::Title, &terms("space war"), 0),
+            vec![1]
+        );
+    }
+}
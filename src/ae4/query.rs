@@ -0,0 +1,556 @@
+use ae4::*;
+
+/// Parsed boolean query. Terms are lower case and stemmed like the movie
+/// descriptions. `Near` is a phrase written as `"a b"~k`, which allows up to
+/// `k` other words between neighbouring terms. `Prefix` is written as `star*`
+/// and `Fuzzy` as `word~k`, matching terms at most `k` edits away (2 if `k`
+/// is left out). `Any` matches any of its terms, it is the result of
+/// expanding the other two. Queries search all fields unless they are scoped
+/// to one with `title:` or `desc:`, like `title:war` or `title:(a OR b)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    Term(String),
+    Phrase(Vec<String>),
+    Near(Vec<String>, u32),
+    Prefix(String),
+    Fuzzy(String, u32),
+    Any(Vec<String>),
+    Field(Field, Box<Query>),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Phrase(Vec<String>, u32),
+    Prefix(String),
+    Fuzzy(String, u32),
+    Field(Field),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+fn tokenize(query: &str, analyzer: &Analyzer) -> Result<Vec<Token>, StrError> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            '"' => {
+                chars.next();
+                let mut phrase = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => phrase.push(c),
+                        None => return Err(StrError { msg: "Unterminated phrase" }),
+                    }
+                }
+                let words = analyzer.analyze(&phrase);
+                if words.is_empty() {
+                    return Err(StrError { msg: "Empty phrase" });
+                }
+                let mut slop = 0;
+                if chars.peek() == Some(&'~') {
+                    chars.next();
+                    let mut digits = String::new();
+                    while let Some(&c) = chars.peek() {
+                        if !c.is_ascii_digit() {
+                            break;
+                        }
+                        digits.push(c);
+                        chars.next();
+                    }
+                    slop = digits.parse().map_err(|_| {
+                        StrError { msg: "Expected a distance after ~" }
+                    })?;
+                }
+                tokens.push(Token::Phrase(words, slop));
+            }
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' || c == '"' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                if let Some(split) = word.find(':') {
+                    if let Some(field) = Field::from_name(&word[..split]) {
+                        tokens.push(Token::Field(field));
+                        word.drain(..split + 1);
+                    }
+                }
+                if !word.is_empty() {
+                    push_word(&word, analyzer, &mut tokens)?;
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Turns a word of the query into a token: an operator, a prefix, a fuzzy
+/// term or the analyzed terms.
+fn push_word(word: &str, analyzer: &Analyzer, tokens: &mut Vec<Token>) -> Result<(), StrError> {
+    match word {
+        "AND" => tokens.push(Token::And),
+        "OR" => tokens.push(Token::Or),
+        "NOT" => tokens.push(Token::Not),
+        _ if word.len() > 1 && word.ends_with('*') => {
+            let prefix = analyzer.normalize(&word[..word.len() - 1]);
+            if prefix.is_empty() {
+                return Err(StrError { msg: "Empty prefix" });
+            }
+            tokens.push(Token::Prefix(prefix));
+        }
+        _ if word.contains('~') => {
+            let split = word.rfind('~').unwrap();
+            let distance = match &word[split + 1..] {
+                "" => 2,
+                digits => digits.parse().map_err(|_| {
+                    StrError { msg: "Expected a distance after ~" }
+                })?,
+            };
+            let mut terms = analyzer.analyze(&word[..split]);
+            if terms.len() != 1 {
+                return Err(StrError { msg: "Fuzzy search needs a single term" });
+            }
+            tokens.push(Token::Fuzzy(terms.remove(0), distance));
+        }
+        _ => {
+            let mut terms = analyzer.analyze(word);
+            match terms.len() {
+                0 => (),
+                1 => tokens.push(Token::Word(terms.remove(0))),
+                _ => tokens.push(Token::Phrase(terms, 0)),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Recursive descent parser, NOT binds stronger than AND, AND stronger than
+/// OR. Terms next to each other are joined by AND.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Query, StrError> {
+        let mut query = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            query = Query::Or(Box::new(query), Box::new(self.and()?));
+        }
+        Ok(query)
+    }
+
+    fn and(&mut self) -> Result<Query, StrError> {
+        let mut query = self.unary()?;
+        loop {
+            match self.peek() {
+                Some(&Token::And) => {
+                    self.next();
+                }
+                Some(&Token::Word(_)) | Some(&Token::Phrase(..)) | Some(&Token::Prefix(_)) |
+                Some(&Token::Fuzzy(..)) | Some(&Token::Field(_)) | Some(&Token::Not) |
+                Some(&Token::Open) => (),
+                _ => return Ok(query),
+            }
+            query = Query::And(Box::new(query), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Query, StrError> {
+        match self.next() {
+            Some(Token::Not) => Ok(Query::Not(Box::new(self.unary()?))),
+            Some(Token::Field(field)) => Ok(Query::Field(field, Box::new(self.unary()?))),
+            Some(Token::Word(word)) => Ok(Query::Term(word)),
+            Some(Token::Prefix(prefix)) => Ok(Query::Prefix(prefix)),
+            Some(Token::Fuzzy(term, distance)) => Ok(Query::Fuzzy(term, distance)),
+            Some(Token::Phrase(mut words, slop)) => {
+                if words.len() == 1 {
+                    Ok(Query::Term(words.remove(0)))
+                } else if slop == 0 {
+                    Ok(Query::Phrase(words))
+                } else {
+                    Ok(Query::Near(words, slop))
+                }
+            }
+            Some(Token::Open) => {
+                let query = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(query),
+                    _ => Err(StrError { msg: "Missing closing parenthesis" }),
+                }
+            }
+            Some(Token::Close) => Err(StrError { msg: "Unexpected closing parenthesis" }),
+            Some(_) => Err(StrError { msg: "Operator without operand" }),
+            None => Err(StrError { msg: "Query ends with an operator" }),
+        }
+    }
+}
+
+/// Parses a query with the default `Analyzer`.
+pub fn parse_query(query: &str) -> Result<Query, StrError> {
+    parse_query_with(query, &Analyzer::default())
+}
+
+/// Parses a query, the words are analyzed like the descriptions: a word
+/// split into several terms becomes a phrase and one consisting only of stop
+/// words is dropped.
+pub fn parse_query_with(query: &str, analyzer: &Analyzer) -> Result<Query, StrError> {
+    let mut parser = Parser {
+        tokens: tokenize(query, analyzer)?,
+        position: 0,
+    };
+    if parser.tokens.is_empty() {
+        return Err(StrError { msg: "Empty query" });
+    }
+    let query = parser.or()?;
+    match parser.peek() {
+        None => Ok(query),
+        Some(&Token::Close) => Err(StrError { msg: "Unexpected closing parenthesis" }),
+        Some(_) => Err(StrError { msg: "Unexpected operator" }),
+    }
+}
+
+impl Query {
+    /// Distinct terms of the query, sorted. Terms below a `Not` are only
+    /// included with `negated`.
+    pub fn terms(&self, negated: bool) -> Vec<&str> {
+        let mut terms = Vec::new();
+        self.collect_terms(negated, &mut terms);
+        terms.sort();
+        terms.dedup();
+        terms
+    }
+
+    /// Whether the query contains a phrase or proximity query.
+    pub fn has_phrase(&self) -> bool {
+        match *self {
+            Query::Phrase(_) | Query::Near(..) => true,
+            Query::Term(_) | Query::Prefix(_) | Query::Fuzzy(..) | Query::Any(_) => false,
+            Query::Field(_, ref a) | Query::Not(ref a) => a.has_phrase(),
+            Query::And(ref a, ref b) | Query::Or(ref a, ref b) => a.has_phrase() || b.has_phrase(),
+        }
+    }
+
+    fn collect_terms<'q>(&'q self, negated: bool, terms: &mut Vec<&'q str>) {
+        match *self {
+            Query::Term(ref term) => terms.push(term),
+            Query::Phrase(ref words) | Query::Near(ref words, _) | Query::Any(ref words) => {
+                terms.extend(words.iter().map(|w| w.as_str()))
+            }
+            Query::Prefix(_) | Query::Fuzzy(..) => (),
+            Query::Field(_, ref a) => a.collect_terms(negated, terms),
+            Query::And(ref a, ref b) | Query::Or(ref a, ref b) => {
+                a.collect_terms(negated, terms);
+                b.collect_terms(negated, terms);
+            }
+            Query::Not(ref a) => {
+                if negated {
+                    a.collect_terms(negated, terms);
+                }
+            }
+        }
+    }
+
+    /// Replaces prefixes and fuzzy terms by the matching terms of the index.
+    /// Unknown terms are replaced by the terms within `auto_distance`, if
+    /// there are any, these corrections are added to `corrections`.
+    pub fn expand(&self, index: &InvertedIndex, corrections: &mut Vec<(String, Vec<String>)>) -> Query {
+        self.expand_in(index, &Field::ALL, true, corrections)
+    }
+
+    /// Like `expand`, but leaves unknown terms as they are, for indexes which
+    /// only hold a part of the movies.
+    pub fn expand_patterns(&self, index: &InvertedIndex) -> Query {
+        self.expand_in(index, &Field::ALL, false, &mut Vec::new())
+    }
+
+    fn expand_in(
+        &self,
+        index: &InvertedIndex,
+        fields: &[Field],
+        correct: bool,
+        corrections: &mut Vec<(String, Vec<String>)>,
+    ) -> Query {
+        let names = |lookup: &dyn Fn(&FieldIndex) -> Vec<usize>| -> Vec<String> {
+            let mut names: Vec<String> = Vec::new();
+            for &field in fields {
+                let field_index = index.field(field);
+                for id in lookup(field_index) {
+                    if !names.contains(&field_index.vocabulary[id]) {
+                        names.push(field_index.vocabulary[id].clone());
+                    }
+                }
+            }
+            names
+        };
+        match *self {
+            Query::Term(ref term)
+                if correct && fields.iter().all(|&f| index.field(f).get(term).is_none()) =>
+            {
+                let found = names(&|f| f.fuzzy_terms(term, auto_distance(term)));
+                if found.is_empty() {
+                    return self.clone();
+                }
+                corrections.push((term.clone(), found.clone()));
+                Query::Any(found)
+            }
+            Query::Prefix(ref prefix) => Query::Any(names(&|f| f.prefix_terms(prefix).collect())),
+            Query::Fuzzy(ref term, distance) => {
+                Query::Any(names(&|f| f.fuzzy_terms(term, distance as usize)))
+            }
+            Query::Field(field, ref a) => {
+                Query::Field(field, Box::new(a.expand_in(index, &[field], correct, corrections)))
+            }
+            Query::And(ref a, ref b) => Query::And(
+                Box::new(a.expand_in(index, fields, correct, corrections)),
+                Box::new(b.expand_in(index, fields, correct, corrections)),
+            ),
+            Query::Or(ref a, ref b) => Query::Or(
+                Box::new(a.expand_in(index, fields, correct, corrections)),
+                Box::new(b.expand_in(index, fields, correct, corrections)),
+            ),
+            Query::Not(ref a) => {
+                Query::Not(Box::new(a.expand_in(index, fields, correct, corrections)))
+            }
+            _ => self.clone(),
+        }
+    }
+
+    /// Splits a chain of `And`s into the operands and the negated operands.
+    fn and_operands<'q>(&'q self, positive: &mut Vec<&'q Query>, negative: &mut Vec<&'q Query>) {
+        match *self {
+            Query::And(ref a, ref b) => {
+                a.and_operands(positive, negative);
+                b.and_operands(positive, negative);
+            }
+            Query::Not(ref a) => negative.push(a),
+            _ => positive.push(self),
+        }
+    }
+
+    /// Ids of all matching movies in ascending order. The operands of an
+    /// `And` are intersected with cursors, terms directly on their compressed
+    /// posting lists. Unknown terms match nothing.
+    pub fn evaluate(&self, index: &InvertedIndex, movies: &[Movie]) -> Vec<usize> {
+        self.evaluate_in(index, movies, &Field::ALL)
+    }
+
+    /// Like `evaluate`, terms match in any of `fields`.
+    fn evaluate_in(&self, index: &InvertedIndex, movies: &[Movie], fields: &[Field]) -> Vec<usize> {
+        match *self {
+            Query::Field(field, ref a) => a.evaluate_in(index, movies, &[field]),
+            Query::And(..) => {
+                let mut positive = Vec::new();
+                let mut negative = Vec::new();
+                self.and_operands(&mut positive, &mut negative);
+                let evaluated: Vec<Vec<usize>> = positive
+                    .iter()
+                    .filter(|q| !matches!(***q, Query::Term(_)))
+                    .map(|q| q.evaluate_in(index, movies, fields))
+                    .collect();
+                let mut evaluated = evaluated.iter();
+                let cursors: Vec<DocCursor> = positive
+                    .iter()
+                    .map(|q| match **q {
+                        Query::Term(ref term) => DocCursor::Union(
+                            fields
+                                .iter()
+                                .filter_map(|&field| index.field(field).get(term))
+                                .map(|list| DocCursor::Compressed(list.docs().cursor()))
+                                .collect(),
+                        ),
+                        _ => DocCursor::Slice(evaluated.next().expect("operand evaluated"), 0),
+                    })
+                    .collect();
+                let mut result = if cursors.is_empty() {
+                    (0..index.doc_count()).collect()
+                } else {
+                    intersect_cursors(cursors)
+                };
+                for query in negative {
+                    result = difference(&result, &query.evaluate_in(index, movies, fields));
+                }
+                result
+            }
+            Query::Or(ref a, ref b) => union(
+                &a.evaluate_in(index, movies, fields),
+                &b.evaluate_in(index, movies, fields),
+            ),
+            Query::Not(ref a) => {
+                let all: Vec<usize> = (0..index.doc_count()).collect();
+                difference(&all, &a.evaluate_in(index, movies, fields))
+            }
+            _ => fields.iter().fold(Vec::new(), |docs, &field| {
+                union(&docs, &self.evaluate_field(index, movies, field))
+            }),
+        }
+    }
+
+    /// Ids of the movies matching a single term, phrase or expansion in
+    /// `field`.
+    fn evaluate_field(&self, index: &InvertedIndex, movies: &[Movie], field: Field) -> Vec<usize> {
+        let field_index = index.field(field);
+        match *self {
+            Query::Term(ref term) => {
+                field_index.get(term).map_or_else(Vec::new, |list| list.docs().decode())
+            }
+            Query::Phrase(ref terms) => proximity_query(index, movies, field, terms, 0),
+            Query::Near(ref terms, slop) => proximity_query(index, movies, field, terms, slop),
+            Query::Prefix(ref prefix) => {
+                field_index.merged_postings(field_index.prefix_terms(prefix))
+            }
+            Query::Fuzzy(ref term, distance) => {
+                field_index.merged_postings(field_index.fuzzy_terms(term, distance as usize))
+            }
+            Query::Any(ref terms) => {
+                field_index.merged_postings(terms.iter().filter_map(|t| field_index.term_id(t)))
+            }
+            _ => self.evaluate_in(index, movies, &[field]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ae4::*;
+    use super::Query;
+    use super::Query::*;
+
+    fn term(word: &str) -> Box<Query> {
+        Box::new(Term(word.to_owned()))
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(parse_query("Space"), Ok(Term("space".to_owned())));
+        assert_eq!(
+            parse_query("space wars OR cowboys"),
+            Ok(Or(Box::new(And(term("space"), term("war"))), term("cowboi")))
+        );
+        assert_eq!(
+            parse_query("space AND (war OR NOT horse)"),
+            Ok(And(term("space"), Box::new(Or(term("war"), Box::new(Not(term("hors")))))))
+        );
+        assert_eq!(
+            parse_query("\"space war\" NOT \"horse\""),
+            Ok(And(
+                Box::new(Phrase(vec!["space".to_owned(), "war".to_owned()])),
+                Box::new(Not(term("hors"))),
+            ))
+        );
+        assert!(parse_query("").is_err());
+        assert!(parse_query("(space war").is_err());
+        assert!(parse_query("space war)").is_err());
+        assert!(parse_query("space OR").is_err());
+        assert!(parse_query("AND war").is_err());
+        assert!(parse_query("\"space war").is_err());
+        assert_eq!(
+            parse_query("\"war space\"~2"),
+            Ok(Near(vec!["war".to_owned(), "space".to_owned()], 2))
+        );
+        assert!(parse_query("\"war space\"~").is_err());
+        assert_eq!(
+            parse_query("Stari* helo~1"),
+            Ok(And(Box::new(Prefix("stari".to_owned())), Box::new(Fuzzy("helo".to_owned(), 1))))
+        );
+        assert_eq!(parse_query("wars~"), Ok(Fuzzy("war".to_owned(), 2)));
+        assert!(parse_query("helo~x").is_err());
+        assert_eq!(
+            parse_query_with("the sci-fi", &Analyzer::english()),
+            Ok(Phrase(vec!["sci".to_owned(), "fi".to_owned()]))
+        );
+        assert_eq!(parse_query("title:Alien"), Ok(Query::Field(::ae4::Field::Title, term("alien"))));
+        assert_eq!(
+            parse_query("title:\"star wars\" desc:space"),
+            Ok(And(
+                Box::new(Query::Field(
+                    ::ae4::Field::Title,
+                    Box::new(Phrase(vec!["star".to_owned(), "war".to_owned()])),
+                )),
+                Box::new(Query::Field(::ae4::Field::Description, term("space"))),
+            ))
+        );
+        assert_eq!(
+            parse_query("title:(war OR horse)"),
+            Ok(Query::Field(::ae4::Field::Title, Box::new(Or(term("war"), term("hors")))))
+        );
+        assert_eq!(parse_query("year:1977"), Ok(Phrase(vec!["year".to_owned(), "1977".to_owned()])));
+        assert!(parse_query("title:").is_err());
+    }
+
+    #[test]
+    fn evaluate() {
+        let movies: Vec<Movie> = [
+            "Space Cowboys\tOld pilots fly to space",
+            "Star Wars\tA war in space between rebels",
+            "War Horse\tA horse in the war",
+            "Space War\tThe space war of the planets",
+        ].iter()
+            .map(|line| line.parse().unwrap())
+            .collect();
+        let index = build_inverted_index(&movies, true);
+        let run = |query: &str| parse_query(query).unwrap().evaluate(&index, &movies);
+
+        assert_eq!(run("space war"), vec![1, 3]);
+        assert_eq!(run("space OR horse"), vec![0, 1, 2, 3]);
+        assert_eq!(run("war NOT space"), vec![2]);
+        assert_eq!(run("NOT war"), vec![0]);
+        assert_eq!(run("\"space war\""), vec![3]);
+        assert_eq!(run("\"war space\"~1 OR horse"), vec![1, 2]);
+        assert_eq!(run("(cowboys OR horse) AND NOT pilots"), vec![2]);
+        assert_eq!(run("space spacecraft"), Vec::<usize>::new());
+
+        let result = query_index(&index, &movies, "war OR spacecraft NOT pilots", None).unwrap();
+        assert_eq!(result.ids, vec![1, 2, 3]);
+        assert_eq!(result.scores, None);
+        assert_eq!(result.unknown_terms, vec!["spacecraft"]);
+        let ranked = query_index(&index, &movies, "war", Some(&Bm25::default())).unwrap();
+        assert_eq!(ranked.ids, vec![2, 1, 3]);
+        assert!(ranked.scores.unwrap()[0] > 0.0);
+        assert!(query_index(&index, &movies, "space OR", None).is_err());
+
+        assert_eq!(run("spa* NOT war"), vec![0]);
+        assert_eq!(run("hors~1 OR planet~"), vec![2, 3]);
+        let corrected = query_index(&index, &movies, "spase war", None).unwrap();
+        assert_eq!(corrected.ids, vec![1, 3]);
+        assert_eq!(corrected.unknown_terms, vec!["spase"]);
+        assert_eq!(corrected.corrections, vec![("spase".to_owned(), vec!["space".to_owned()])]);
+
+        assert_eq!(run("title:war"), vec![1, 2, 3]);
+        assert_eq!(run("title:space"), vec![0, 3]);
+        assert_eq!(run("cowboys"), vec![0]);
+        assert_eq!(run("desc:cowboys"), Vec::<usize>::new());
+        assert_eq!(run("title:\"space war\""), vec![3]);
+        assert_eq!(run("title:(star OR horse) space"), vec![1]);
+        assert_eq!(run("desc:plan*"), vec![3]);
+    }
+}
@@ -0,0 +1,162 @@
+use ae4::*;
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Parameters of the Okapi BM25 ranking function: `k1` limits the influence
+/// of repeated terms, `b` how much long texts are penalised. The score of a
+/// movie is the sum of the scores of its fields, each multiplied by the
+/// boost of the field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bm25 {
+    pub k1: f64,
+    pub b: f64,
+    pub title_boost: f64,
+    pub description_boost: f64,
+}
+
+impl Default for Bm25 {
+    fn default() -> Bm25 {
+        Bm25 {
+            k1: 1.2,
+            b: 0.75,
+            title_boost: 2.0,
+            description_boost: 1.0,
+        }
+    }
+}
+
+impl Bm25 {
+    pub fn boost(&self, field: Field) -> f64 {
+        match field {
+            Field::Title => self.title_boost,
+            Field::Description => self.description_boost,
+        }
+    }
+
+    /// Inverse document frequency of a term occurring in `df` of `n`
+    /// documents, never negative.
+    pub fn idf(&self, n: usize, df: usize) -> f64 {
+        (1.0 + (n as f64 - df as f64 + 0.5) / (df as f64 + 0.5)).ln()
+    }
+
+    pub fn score(&self, idf: f64, freq: u32, doc_length: u32, average_length: f64) -> f64 {
+        let freq = f64::from(freq);
+        let norm = 1.0 - self.b + self.b * f64::from(doc_length) / average_length.max(1.0);
+        idf * freq * (self.k1 + 1.0) / (freq + self.k1 * norm)
+    }
+}
+
+/// Ids of the `k` movies with the highest BM25 score for `query`, best
+/// first. Every movie containing at least one of the query terms is a
+/// candidate; ties are broken by id.
+pub fn ranked_query(index: &InvertedIndex, query: &str, k: usize, params: &Bm25) -> Vec<(usize, f64)> {
+    let mut scores: HashMap<usize, f64> = HashMap::new();
+    let mut terms = index.analyzer.analyze(query);
+    terms.sort();
+    terms.dedup();
+
+    for &field in &Field::ALL {
+        let field_index = index.field(field);
+        let average_length = field_index.average_length();
+        let boost = params.boost(field);
+        for term in &terms {
+            let postings = match field_index.get(term) {
+                Some(postings) => postings,
+                None => continue,
+            };
+            let idf = params.idf(index.doc_count(), postings.len());
+            let mut cursor = postings.cursor();
+            while let Some(doc) = cursor.next() {
+                let length = field_index.lengths[doc];
+                *scores.entry(doc).or_insert(0.0) +=
+                    boost * params.score(idf, cursor.freq(), length, average_length);
+            }
+        }
+    }
+
+    let mut ranked: Vec<(usize, f64)> = scores.into_iter().collect();
+    let by_score = |a: &(usize, f64), b: &(usize, f64)| {
+        b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal).then(a.0.cmp(&b.0))
+    };
+    if ranked.len() > k && k > 0 {
+        ranked.select_nth_unstable_by(k - 1, by_score);
+    }
+    ranked.truncate(k);
+    ranked.sort_by(by_score);
+    ranked
+}
+
+/// BM25 scores of the ascending `docs` for `terms`, a term missing from a
+/// document adds nothing to its score.
+pub fn score_docs(index: &InvertedIndex, docs: &[usize], terms: &[&str], params: &Bm25) -> Vec<f64> {
+    let mut scores = vec![0.0; docs.len()];
+    for &field in &Field::ALL {
+        let field_index = index.field(field);
+        let average_length = field_index.average_length();
+        let boost = params.boost(field);
+        for term in terms {
+            let postings = match field_index.get(term) {
+                Some(postings) => postings,
+                None => continue,
+            };
+            let idf = params.idf(index.doc_count(), postings.len());
+            let mut cursor = postings.cursor();
+            for (score, &doc) in scores.iter_mut().zip(docs) {
+                if cursor.advance_to(doc) == Some(doc) {
+                    let length = field_index.lengths[doc];
+                    *score += boost * params.score(idf, cursor.freq(), length, average_length);
+                }
+            }
+        }
+    }
+    scores
+}
+
+#[test]
+fn bm25_ranking() {
+    let movies: Vec<Movie> = [
+        "Space Cowboys\tOld pilots fly to space",
+        "Star Wars\tA war in space between rebels and an empire, the space war of all wars",
+        "War Horse\tA horse in the war",
+        "Cooking\tA movie about cooking pasta",
+        "Long War\tA war that lasts for a very long time while people wait and wait and wait",
+    ].iter()
+        .map(|line| line.parse().unwrap())
+        .collect();
+    let index = build_inverted_index(&movies, false);
+    assert_eq!(index.get("war").map(|p| p.len()), Some(3));
+    assert_eq!(
+        index.get("war").unwrap().iter().next().unwrap(),
+        Posting {
+            doc: 1,
+            freq: 3,
+            positions: Vec::new(),
+        }
+    );
+    assert_eq!(index.field(Field::Description).lengths[2], 5);
+    assert_eq!(index.field(Field::Title).lengths[2], 2);
+
+    let description_only = Bm25 {
+        title_boost: 0.0,
+        ..Bm25::default()
+    };
+    let results = ranked_query(&index, "Space War", 10, &description_only);
+    let titles: Vec<&str> = results
+        .iter()
+        .map(|&(id, _)| index.documents[id].title.as_str())
+        .collect();
+    assert_eq!(titles, vec!["Star Wars", "Space Cowboys", "War Horse", "Long War"]);
+    assert!(results.windows(2).all(|w| w[0].1 >= w[1].1));
+
+    let top = ranked_query(&index, "space war", 2, &description_only);
+    assert_eq!(top.len(), 2);
+    assert_eq!(top[1].0, 0);
+    assert!(ranked_query(&index, "submarine", 5, &Bm25::default()).is_empty());
+
+    let star = ranked_query(&index, "star", 5, &Bm25::default());
+    assert_eq!(star.len(), 1);
+    assert_eq!(star[0].0, 1);
+    let boosted = ranked_query(&index, "long war", 5, &Bm25::default());
+    assert_eq!(boosted[0].0, 4);
+}
@@ -0,0 +1,288 @@
+use ae4::*;
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::slice;
+use std::time::Instant;
+
+/// A segment is merged with the one added after it while it has at most
+/// this many times as many live documents. Every merge then grows the
+/// segment of a document by half at least, so a document is merged only
+/// logarithmically often.
+const MERGE_RATIO: usize = 2;
+
+/// Immutable index of some of the movies. `ids` maps its document numbers
+/// to the ids of the `SegmentedIndex`, `deleted` marks the documents deleted
+/// after the segment was built.
+#[derive(Debug, HeapSizeOf)]
+struct Segment {
+    index: InvertedIndex,
+    ids: Vec<usize>,
+    deleted: Vec<bool>,
+    deleted_count: usize,
+}
+
+impl Segment {
+    fn live_count(&self) -> usize {
+        self.ids.len() - self.deleted_count
+    }
+
+    /// Whether a live document contains the term in any field.
+    fn contains_live(&self, term: &str) -> bool {
+        self.index.fields.iter().any(|field| {
+            field.get(term).is_some_and(|list| list.cursor().any(|doc| !self.deleted[doc]))
+        })
+    }
+}
+
+/// Index which is updated instead of rebuilt. Added movies form a new
+/// segment and deleting a movie only leaves a tombstone, which hides it from
+/// queries until its segment is merged. Ids are assigned in ascending order
+/// and stay valid when segments are merged. The segments are positional, so
+/// phrase queries need no movie texts.
+#[derive(Debug, HeapSizeOf)]
+pub struct SegmentedIndex {
+    segments: Vec<Segment>,
+    next_id: usize,
+    pub analyzer: Analyzer,
+}
+
+impl SegmentedIndex {
+    pub fn new(analyzer: Analyzer) -> SegmentedIndex {
+        SegmentedIndex {
+            segments: Vec::new(),
+            next_id: 0,
+            analyzer,
+        }
+    }
+
+    /// Adds a movie and returns its id.
+    pub fn add(&mut self, movie: &Movie) -> usize {
+        self.add_all(slice::from_ref(movie)).start
+    }
+
+    /// Adds the movies as one segment and returns their ids. Afterwards the
+    /// newest segments are merged as long as they are of similar size.
+    pub fn add_all(&mut self, movies: &[Movie]) -> Range<usize> {
+        let ids = self.next_id..self.next_id + movies.len();
+        if movies.is_empty() {
+            return ids;
+        }
+        self.next_id = ids.end;
+        self.segments.push(Segment {
+            index: index_movies(movies, true, self.analyzer.clone()),
+            ids: ids.clone().collect(),
+            deleted: vec![false; movies.len()],
+            deleted_count: 0,
+        });
+        while self.segments.len() > 1 {
+            let n = self.segments.len();
+            if self.segments[n - 2].live_count() > self.segments[n - 1].live_count() * MERGE_RATIO {
+                break;
+            }
+            let tail = self.segments.split_off(n - 2);
+            self.merge_segments(tail);
+        }
+        ids
+    }
+
+    /// Segment and document number of a live movie. The segments hold
+    /// ascending ranges of ids, so both are found by binary search.
+    fn locate(&self, id: usize) -> Option<(usize, usize)> {
+        let i = self.segments.partition_point(|s| s.ids.last().is_some_and(|&last| last < id));
+        let segment = self.segments.get(i)?;
+        match segment.ids.binary_search(&id) {
+            Ok(doc) if !segment.deleted[doc] => Some((i, doc)),
+            _ => None,
+        }
+    }
+
+    /// Marks the movie as deleted, false if there is no such live movie.
+    pub fn delete(&mut self, id: usize) -> bool {
+        match self.locate(id) {
+            Some((i, doc)) => {
+                let segment = &mut self.segments[i];
+                segment.deleted[doc] = true;
+                segment.deleted_count += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Merges all segments into one, which drops the deleted movies for good.
+    pub fn merge(&mut self) {
+        let segments = ::std::mem::take(&mut self.segments);
+        self.merge_segments(segments);
+    }
+
+    /// Appends the merge of consecutive `segments`, unless no movie is left.
+    fn merge_segments(&mut self, segments: Vec<Segment>) {
+        let mut ids = Vec::new();
+        let mut documents = Vec::new();
+        let mut terms: Vec<BTreeMap<String, PostingList>> =
+            Field::ALL.iter().map(|_| BTreeMap::new()).collect();
+        let mut lengths: Vec<Vec<u32>> = Field::ALL.iter().map(|_| Vec::new()).collect();
+        for segment in segments {
+            let mut docs = Vec::with_capacity(segment.ids.len());
+            for (doc, document) in segment.index.documents.into_iter().enumerate() {
+                if segment.deleted[doc] {
+                    docs.push(None);
+                } else {
+                    docs.push(Some(ids.len()));
+                    ids.push(segment.ids[doc]);
+                    documents.push(document);
+                }
+            }
+            for (i, field_index) in segment.index.fields.into_iter().enumerate() {
+                lengths[i].extend(
+                    field_index.lengths
+                        .into_iter()
+                        .enumerate()
+                        .filter(|&(doc, _)| docs[doc].is_some())
+                        .map(|(_, length)| length),
+                );
+                for (term, postings) in field_index.vocabulary.into_iter().zip(field_index.postings) {
+                    let merged = terms[i].entry(term).or_insert_with(|| PostingList::new(true));
+                    for posting in postings.iter() {
+                        if let Some(doc) = docs[posting.doc] {
+                            merged.push(doc, posting.freq, &posting.positions);
+                        }
+                    }
+                }
+            }
+        }
+        if ids.is_empty() {
+            return;
+        }
+
+        let fields = terms
+            .into_iter()
+            .zip(lengths)
+            .map(|(terms, lengths)| {
+                let (vocabulary, postings) = terms
+                    .into_iter()
+                    .filter(|(_, postings)| !postings.is_empty())
+                    .map(|(term, mut postings)| {
+                        postings.shrink_to_fit();
+                        (term, postings)
+                    })
+                    .unzip();
+                FieldIndex {
+                    vocabulary,
+                    postings,
+                    lengths,
+                }
+            })
+            .collect();
+        self.segments.push(Segment {
+            index: InvertedIndex {
+                fields,
+                documents,
+                positional: true,
+                analyzer: self.analyzer.clone(),
+            },
+            deleted: vec![false; ids.len()],
+            ids,
+            deleted_count: 0,
+        });
+    }
+
+    /// Number of live movies.
+    pub fn len(&self) -> usize {
+        self.segments.iter().map(|s| s.live_count()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn title(&self, id: usize) -> Option<&str> {
+        self.locate(id)
+            .map(|(i, doc)| self.segments[i].index.documents[doc].title.as_str())
+    }
+
+    /// Evaluates a boolean query on every segment, like `query_index` without
+    /// ranking and corrections of unknown terms. The ids are ascending. Terms
+    /// which only occur in deleted movies are unknown too.
+    pub fn query(&self, query: &str) -> Result<QueryResult, StrError> {
+        let start = Instant::now();
+        let parsed = parse_query_with(query, &self.analyzer)?;
+        let unknown_terms = parsed
+            .terms(true)
+            .into_iter()
+            .filter(|term| !self.segments.iter().any(|s| s.contains_live(term)))
+            .map(|term| term.to_owned())
+            .collect();
+        let mut ids = Vec::new();
+        for segment in &self.segments {
+            let docs = parsed.expand_patterns(&segment.index).evaluate(&segment.index, &[]);
+            ids.extend(
+                docs.into_iter()
+                    .filter(|&doc| !segment.deleted[doc])
+                    .map(|doc| segment.ids[doc]),
+            );
+        }
+
+        Ok(QueryResult {
+            ids,
+            scores: None,
+            unknown_terms,
+            corrections: Vec::new(),
+            duration: Instant::now().duration_since(start),
+        })
+    }
+}
+
+#[test]
+fn add_delete_and_merge() {
+    let lines = [
+        "Space Cowboys\tOld pilots fly to space",
+        "Star Wars\tA war in space between rebels",
+        "War Horse\tA horse in the war",
+        "Space War\tThe space war of the planets",
+        "Starman\tAn alien stranded on earth",
+    ];
+    let movies: Vec<Movie> = lines.iter().map(|line| line.parse().unwrap()).collect();
+    let mut index = SegmentedIndex::new(Analyzer::english());
+    for (i, movie) in movies[..4].iter().enumerate() {
+        assert_eq!(index.add(movie), i);
+    }
+    assert_eq!(index.segment_count(), 2);
+    assert_eq!(index.add_all(&movies[4..]), 4..5);
+    assert_eq!(index.segment_count(), 1);
+    let run = |index: &SegmentedIndex, query: &str| index.query(query).unwrap().ids;
+    assert_eq!(run(&index, "space war"), vec![1, 3]);
+    assert_eq!(run(&index, "star* NOT war"), vec![4]);
+
+    assert!(index.delete(3));
+    assert!(!index.delete(3));
+    assert!(!index.delete(7));
+    assert_eq!(index.len(), 4);
+    assert_eq!(index.title(3), None);
+    assert_eq!(run(&index, "space war"), vec![1]);
+    assert_eq!(run(&index, "\"space war\""), Vec::<usize>::new());
+    assert_eq!(run(&index, "NOT war"), vec![0, 4]);
+    assert_eq!(index.query("planets war").unwrap().unknown_terms, vec!["planet"]);
+
+    let added = index.add(&movies[3]);
+    assert_eq!(added, 5);
+    index.merge();
+    assert_eq!(index.segment_count(), 1);
+    assert_eq!(index.title(5), Some("Space War"));
+    assert_eq!(run(&index, "\"space war\""), vec![5]);
+    let live: Vec<Movie> = [0, 1, 2, 4, 3].iter().map(|&i| lines[i].parse().unwrap()).collect();
+    assert_eq!(index.segments[0].index, index_movies(&live, true, Analyzer::english()));
+
+    for id in 0..6 {
+        index.delete(id);
+    }
+    index.merge();
+    assert!(index.is_empty());
+    assert_eq!(index.segment_count(), 0);
+    assert_eq!(index.query("war").unwrap().unknown_terms, vec!["war"]);
+}
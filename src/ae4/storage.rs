@@ -0,0 +1,274 @@
+use ae4::*;
+use ae1::write_varint;
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"MIDX";
+
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn write_str(bytes: &mut Vec<u8>, s: &str) {
+    write_varint(bytes, s.len() as u64);
+    bytes.extend_from_slice(s.as_bytes());
+}
+
+/// Reads varints like `ae1::read_varint`, but fails on truncated input
+/// instead of panicking.
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn varint(&mut self) -> io::Result<u64> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = *self.bytes.get(self.position).ok_or_else(|| invalid("Truncated index"))?;
+            self.position += 1;
+            if shift > 63 {
+                return Err(invalid("Varint too long"));
+            }
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte < 0x80 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let value = self.varint()?;
+        if value > u64::from(u32::MAX) {
+            return Err(invalid("Value out of range"));
+        }
+        Ok(value as u32)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.varint()? as usize;
+        if len > self.bytes.len() - self.position {
+            return Err(invalid("Truncated index"));
+        }
+        let s = ::std::str::from_utf8(&self.bytes[self.position..self.position + len])
+            .map_err(|_| invalid("Invalid utf-8"))?;
+        self.position += len;
+        Ok(s.to_owned())
+    }
+}
+
+fn write_field(bytes: &mut Vec<u8>, field: &FieldIndex) {
+    for &length in &field.lengths {
+        write_varint(bytes, u64::from(length));
+    }
+    write_varint(bytes, field.vocabulary.len() as u64);
+    for (term, postings) in field.vocabulary.iter().zip(&field.postings) {
+        write_str(bytes, term);
+        write_varint(bytes, postings.len() as u64);
+        let mut last_doc = 0;
+        for posting in postings.iter() {
+            write_varint(bytes, (posting.doc - last_doc) as u64);
+            write_varint(bytes, u64::from(posting.freq));
+            last_doc = posting.doc;
+            let mut last_position = 0;
+            for &position in &posting.positions {
+                write_varint(bytes, u64::from(position - last_position));
+                last_position = position;
+            }
+        }
+    }
+}
+
+fn read_field(decoder: &mut Decoder, doc_count: usize, positional: bool) -> io::Result<FieldIndex> {
+    let limit = decoder.bytes.len();
+    let mut lengths = Vec::with_capacity(doc_count.min(limit));
+    for _ in 0..doc_count {
+        lengths.push(decoder.u32()?);
+    }
+    let term_count = decoder.varint()? as usize;
+    let mut vocabulary: Vec<String> = Vec::with_capacity(term_count.min(limit));
+    let mut postings = Vec::with_capacity(term_count.min(limit));
+    for _ in 0..term_count {
+        let term = decoder.string()?;
+        if vocabulary.last().is_some_and(|last| *last >= term) {
+            return Err(invalid("Vocabulary not sorted"));
+        }
+        let len = decoder.varint()? as usize;
+        let mut list = PostingList::new(positional);
+        let mut doc: usize = 0;
+        for i in 0..len {
+            let delta = decoder.varint()? as usize;
+            if i > 0 && delta == 0 {
+                return Err(invalid("Posting list not sorted"));
+            }
+            doc = doc.checked_add(delta).ok_or_else(|| invalid("Document id out of range"))?;
+            if doc >= doc_count {
+                return Err(invalid("Document id out of range"));
+            }
+            let freq = decoder.u32()?;
+            let mut positions = Vec::new();
+            if positional {
+                let mut position: u32 = 0;
+                for _ in 0..freq {
+                    position = position
+                        .checked_add(decoder.u32()?)
+                        .ok_or_else(|| invalid("Position out of range"))?;
+                    positions.push(position);
+                }
+            }
+            list.push(doc, freq, &positions);
+        }
+        list.shrink_to_fit();
+        vocabulary.push(term);
+        postings.push(list);
+    }
+    Ok(FieldIndex {
+        vocabulary,
+        postings,
+        lengths,
+    })
+}
+
+impl InvertedIndex {
+    /// Writes the index in a compact binary format: the magic bytes `MIDX`, a
+    /// byte which is 1 for a positional index, then varints for the analyzer
+    /// (stemming, n-gram length or 0, sorted stop words), the document table
+    /// (title of every document) and the number of fields. Every field
+    /// consists of the document lengths and the vocabulary sorted by term,
+    /// each followed by its posting list. Document ids and positions are
+    /// stored as differences to their predecessor.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        write_varint(&mut bytes, self.analyzer.stemming as u64);
+        write_varint(&mut bytes, self.analyzer.ngram.unwrap_or(0) as u64);
+        let mut stop_words: Vec<&String> = self.analyzer.stop_words.iter().collect();
+        stop_words.sort();
+        write_varint(&mut bytes, stop_words.len() as u64);
+        for word in stop_words {
+            write_str(&mut bytes, word);
+        }
+
+        write_varint(&mut bytes, self.documents.len() as u64);
+        for document in &self.documents {
+            write_str(&mut bytes, &document.title);
+        }
+        write_varint(&mut bytes, self.fields.len() as u64);
+        for field in &self.fields {
+            write_field(&mut bytes, field);
+        }
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[self.positional as u8])?;
+        writer.write_all(&bytes)
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<InvertedIndex> {
+        let mut header = [0; 5];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(invalid("Not an index file"));
+        }
+        let positional = match header[4] {
+            0 => false,
+            1 => true,
+            _ => return Err(invalid("Unknown index flags")),
+        };
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let mut decoder = Decoder {
+            bytes: &bytes,
+            position: 0,
+        };
+
+        let stemming = match decoder.varint()? {
+            0 => false,
+            1 => true,
+            _ => return Err(invalid("Unknown analyzer flags")),
+        };
+        let ngram = match decoder.varint()? as usize {
+            0 => None,
+            n => Some(n),
+        };
+        let stop_word_count = decoder.varint()? as usize;
+        let mut stop_words = HashSet::with_capacity(stop_word_count.min(bytes.len()));
+        for _ in 0..stop_word_count {
+            stop_words.insert(decoder.string()?);
+        }
+        let analyzer = Analyzer {
+            stop_words,
+            stemming,
+            ngram,
+        };
+
+        let doc_count = decoder.varint()? as usize;
+        let mut documents = Vec::with_capacity(doc_count.min(bytes.len()));
+        for _ in 0..doc_count {
+            documents.push(Document { title: decoder.string()? });
+        }
+        if decoder.varint()? != Field::ALL.len() as u64 {
+            return Err(invalid("Wrong number of fields"));
+        }
+        let mut fields = Vec::with_capacity(Field::ALL.len());
+        for _ in 0..Field::ALL.len() {
+            fields.push(read_field(&mut decoder, doc_count, positional)?);
+        }
+        if decoder.position != bytes.len() {
+            return Err(invalid("Trailing data after index"));
+        }
+
+        Ok(InvertedIndex {
+            fields,
+            documents,
+            positional,
+            analyzer,
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, file: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(file)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(file: P) -> io::Result<InvertedIndex> {
+        InvertedIndex::read_from(&mut BufReader::new(File::open(file)?))
+    }
+}
+
+#[test]
+fn index_round_trip() {
+    let movies: Vec<Movie> = [
+        "Star Wars\tA war in space between rebels and the empire",
+        "War Horse\tA horse in the war",
+        "Cooking\tA movie about cooking pasta",
+    ].iter()
+        .map(|line| line.parse().unwrap())
+        .collect();
+
+    for &positions in &[true, false] {
+        let analyzer = if positions {
+            Analyzer::default()
+        } else {
+            Analyzer {
+                ngram: Some(4),
+                ..Analyzer::english()
+            }
+        };
+        let index = build_inverted_index_with(&movies, positions, analyzer);
+        let mut bytes = Vec::new();
+        index.write_to(&mut bytes).unwrap();
+        let loaded = InvertedIndex::read_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(loaded.documents, index.documents);
+        assert_eq!(loaded.positional, positions);
+        assert_eq!(loaded.analyzer, index.analyzer);
+        assert_eq!(loaded.fields, index.fields);
+
+        bytes.truncate(bytes.len() - 1);
+        assert!(InvertedIndex::read_from(&mut bytes.as_slice()).is_err());
+    }
+}
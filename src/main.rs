@@ -8,6 +8,7 @@ extern crate heapsize_derive;
 extern crate heapsize;
 extern crate rand;
 extern crate porter_stemmer;
+extern crate unicode_segmentation;
 extern crate ndarray;
 
 use rand::Rng;
@@ -56,6 +57,111 @@ fn ae1_main() {
 
 
 }
+#[allow(dead_code)]
+fn ae1_osm_main() {
+    use std::env;
+
+    let path = env::args().nth(1).expect("expect file argument");
+    let graph = ae1::load_osm_xml(path);
+    println!("#Connected components: {}", graph.count_components());
+}
+
+#[allow(dead_code)]
+fn ae1_export_main() {
+    let graph = ae1::load_graph("/home/flo/workspaces/rust/graphdata/saarland.graph");
+    ae1::write_dimacs(&graph, "saarland.gr", "saarland.co").expect("writing DIMACS failed");
+    let dimacs = ae1::load_dimacs("saarland.gr", Some("saarland.co"));
+    ae1::write_graph(&dimacs, "saarland_dimacs.graph").expect("writing graph failed");
+    println!("#Connected components: {}", dimacs.count_components());
+
+    let bbox = ae1::BoundingBox::new(49.2, 49.3, 6.9, 7.1);
+    let (saarbruecken, _) = graph.crop(&bbox);
+    ae1::write_graph(&saarbruecken, "saarbruecken.graph").expect("writing graph failed");
+}
+
+#[allow(dead_code)]
+fn ae1_bicycle_main() {
+    let graph = ae1::load_graph("/home/flo/workspaces/rust/graphdata/bw.graph");
+    let options = ae1::QueryOptions::bicycle();
+
+    let tries = 10;
+    let mut rng = rand::thread_rng();
+    let mut dijkstra = graph.dijkstra();
+    for _ in 0..tries {
+        let source: NodeId = rng.gen();
+        let dest: NodeId = rng.gen();
+        let source = source % graph.node_count();
+        let dest = dest % graph.node_count();
+        if let Some((cost, path)) = dijkstra.distance_with(source, dest, &options) {
+            let summary: ae1::RouteSummary = graph.route_summary(&path);
+            println!("{} -> {}: cost {}, {:?}", source, dest, cost, summary);
+        }
+    }
+}
+
+#[allow(dead_code)]
+fn ae1_turn_main() {
+    let graph = ae1::load_graph("/home/flo/workspaces/rust/graphdata/saarland.graph");
+    let restrictions =
+        ae1::load_turn_restrictions("/home/flo/workspaces/rust/graphdata/saarland.turns");
+    let only = restrictions
+        .iter()
+        .filter(|r| matches!(**r, ae1::TurnRestriction::Only { .. }))
+        .count();
+    println!("{} restrictions, {} of them only-turns", restrictions.len(), only);
+    let edge_graph = ae1::EdgeGraph::new(&graph, &restrictions, None);
+
+    let tries = 100;
+    let mut rng = rand::thread_rng();
+    let mut dijkstra = graph.dijkstra();
+    let mut edge_dijkstra = edge_graph.dijkstra();
+    let mut longer = 0;
+    for _ in 0..tries {
+        let source: NodeId = rng.gen();
+        let dest: NodeId = rng.gen();
+        let source = source % graph.node_count();
+        let dest = dest % graph.node_count();
+        let plain = dijkstra.distance(source, dest).map(|(d, _)| d);
+        let no_u_turns = edge_dijkstra.distance(source, dest).map(|(d, _)| d);
+        if plain != no_u_turns {
+            longer += 1;
+        }
+    }
+    println!("{} of {} routes need a detour", longer, tries);
+}
+
+#[allow(dead_code)]
+fn ae1_alternatives_main() {
+    let graph = ae1::load_graph("/home/flo/workspaces/rust/graphdata/saarland.graph");
+
+    let tries = 10;
+    let mut rng = rand::thread_rng();
+    for _ in 0..tries {
+        let source: NodeId = rng.gen();
+        let dest: NodeId = rng.gen();
+        let source = source % graph.node_count();
+        let dest = dest % graph.node_count();
+
+        let start = Instant::now();
+        let k_shortest: Vec<ae1::Route> = graph.k_shortest_paths(source, dest, 3);
+        let yen = Instant::now();
+        let alternatives = graph.alternative_routes(source, dest, &ae1::AlternativeOptions::default());
+        let penalty = Instant::now();
+        println!("{} -> {}", source, dest);
+        for route in &k_shortest {
+            println!("  yen:     {} m, {} nodes", route.length, route.path.len());
+        }
+        for route in &alternatives {
+            println!("  penalty: {} m, {} nodes", route.length, route.path.len());
+        }
+        println!(
+            "  yen took {:?}, penalty method took {:?}",
+            yen.duration_since(start),
+            penalty.duration_since(yen)
+        );
+    }
+}
+
 #[allow(dead_code)]
 fn ae2_main() {
 
@@ -88,6 +194,301 @@ fn ae2_main() {
     );
 }
 
+#[allow(dead_code)]
+fn ae1_isochrone_main() {
+    let graph = ae1::load_graph("/home/flo/workspaces/rust/graphdata/saarland.graph");
+
+    let mut rng = rand::thread_rng();
+    let source: NodeId = rng.gen();
+    let source = source % graph.node_count();
+    let start = Instant::now();
+    let isochrone = graph.isochrone(source, 10_000);
+    let search = Instant::now();
+    let points = graph.isochrone_points(&isochrone);
+    let polygon = ae1::concave_hull(&points, 2.0);
+    let hull = Instant::now();
+    println!(
+        "{} nodes and {} boundary edges reached, polygon has {} points",
+        isochrone.nodes.len(),
+        isochrone.boundary.len(),
+        polygon.len()
+    );
+    println!(
+        "search took {:?}, hull took {:?}",
+        search.duration_since(start),
+        hull.duration_since(search)
+    );
+    std::fs::write("isochrone.geojson", ae1::polygon_to_geojson(&polygon)).expect("Could not write GeoJSON");
+}
+
+#[allow(dead_code)]
+fn ae1_reverse_main() {
+    use ae1::Direction;
+    let graph = ae1::load_graph("/home/flo/workspaces/rust/graphdata/saarland.graph");
+
+    let mut rng = rand::thread_rng();
+    let target: NodeId = rng.gen();
+    let target = target % graph.node_count();
+
+    let start = Instant::now();
+    let reaching = graph.reaching(target);
+    let component = graph.strong_component_of(target);
+    let tree = graph.shortest_path_tree(target, Direction::Backward);
+    let end = Instant::now();
+    let farthest = reaching.iter().cloned().max_by_key(|&n| tree.distance(n));
+    println!(
+        "{} nodes can reach {}, {} of them in its strong component",
+        reaching.len(),
+        target,
+        component.len()
+    );
+    if let Some(node) = farthest {
+        println!(
+            "farthest origin {} needs {:?} m to {} over {} nodes",
+            node,
+            tree.distance(node),
+            tree.root,
+            tree.path(node).map_or(0, |p| p.len())
+        );
+    }
+    println!("backward searches took {:?}", end.duration_since(start));
+
+    let reversed = graph.reversed();
+    let forward = reversed.shortest_path_tree(target, Direction::Forward);
+    assert!((0..graph.node_count()).all(|n| forward.distance(n) == tree.distance(n)));
+
+    let isochrone = graph.reverse_isochrone(target, 10_000);
+    let polygon = ae1::concave_hull(&graph.isochrone_points(&isochrone), 2.0);
+    std::fs::write("catchment.geojson", ae1::polygon_to_geojson(&polygon)).expect("Could not write GeoJSON");
+}
+
+#[allow(dead_code)]
+fn check_main() {
+    let stats: ae1::GraphStats = ae1::check_graph_file("/home/flo/workspaces/rust/graphdata/saarland.graph")
+        .expect("Could not read graph file");
+    print!("{}", stats);
+    let stats: ae2::ChStats = ae2::check_ch_file("/home/flo/workspaces/rust/graphdata/bw.ch");
+    print!("{}", stats);
+
+    let graph = ae1::load_graph("/home/flo/workspaces/rust/graphdata/saarland.graph");
+    if let Err(problems) = graph.validate() {
+        println!("graph has {} problems", problems.len());
+    }
+    let ch = ae2::load_graph("/home/flo/workspaces/rust/graphdata/bw.ch");
+    match ch.validate() {
+        Ok(stats) => println!("hierarchy is consistent, {} shortcuts", stats.shortcuts),
+        Err(problems) => println!("hierarchy has problems: {:?}", problems),
+    }
+}
+
+#[allow(dead_code)]
+fn reorder_main() {
+    use ae1::NodeOrder;
+    let graph = ae1::load_graph("/home/flo/workspaces/rust/graphdata/saarland.graph");
+
+    let tries = 100;
+    let mut rng = rand::thread_rng();
+    let queries: Vec<(NodeId, NodeId)> = (0..tries)
+        .map(|_| {
+            let source: NodeId = rng.gen();
+            let dest: NodeId = rng.gen();
+            (source % graph.node_count(), dest % graph.node_count())
+        })
+        .collect();
+
+    let start = Instant::now();
+    let mut dijkstra = graph.dijkstra();
+    for &(source, dest) in &queries {
+        dijkstra.distance(source, dest);
+    }
+    println!("original order: {:?}", start.elapsed() / tries);
+
+    for &order in &[NodeOrder::Bfs, NodeOrder::Dfs, NodeOrder::Hilbert] {
+        let (reordered, mapping) = graph.reordered(order);
+        let start = Instant::now();
+        let mut dijkstra = reordered.dijkstra();
+        for &(source, dest) in &queries {
+            dijkstra.distance(mapping.new_id(source), mapping.new_id(dest));
+        }
+        println!("{:?} order: {:?}", order, start.elapsed() / tries);
+    }
+
+    let ch = ae2::load_graph("/home/flo/workspaces/rust/graphdata/bw.ch");
+    let (reordered, mapping) = ch.reordered_by_level();
+    let mut original = ch.dijkstra();
+    let mut dijkstra = reordered.dijkstra();
+    for &(source, dest) in &queries {
+        assert_eq!(
+            original.distance(source, dest),
+            dijkstra.distance(mapping.new_id(source), mapping.new_id(dest))
+        );
+    }
+}
+
+#[allow(dead_code)]
+fn compact_main() {
+    use ae1::EdgeLayout;
+    let graph = ae1::load_graph("/home/flo/workspaces/rust/graphdata/bw.graph");
+    for (layout, bytes) in ae1::memory_report(&graph).expect("graph too large for u32") {
+        println!("{:<36} {} MB", layout, bytes / 1048576);
+    }
+
+    let compact: ae1::CompactGraph =
+        ae1::load_compact_graph("/home/flo/workspaces/rust/graphdata/bw.graph", EdgeLayout::Delta)
+            .expect("graph too large for u32");
+    let node = compact.node_info(0);
+    println!(
+        "first node at {} {} with {} ingoing edges",
+        node.lat(),
+        node.long(),
+        compact.ingoing_edges_for(0).count()
+    );
+    let tries = 100;
+    let mut rng = rand::thread_rng();
+    let queries: Vec<(NodeId, NodeId)> = (0..tries)
+        .map(|_| {
+            let source: NodeId = rng.gen();
+            let dest: NodeId = rng.gen();
+            (source % graph.node_count(), dest % graph.node_count())
+        })
+        .collect();
+    let start = Instant::now();
+    let mut dijkstra = graph.dijkstra();
+    for &(source, dest) in &queries {
+        dijkstra.distance(source, dest);
+    }
+    let usize_graph = Instant::now();
+    for &(source, dest) in &queries {
+        compact.distance(source as u32, dest as u32);
+    }
+    let end = Instant::now();
+    println!(
+        "{} dijkstras took {:?} on the usize graph and {:?} on the {:?} compact graph",
+        tries,
+        usize_graph.duration_since(start),
+        end.duration_since(usize_graph),
+        compact.layout()
+    );
+}
+
+#[allow(dead_code)]
+fn hub_label_main() {
+    let graph = ae2::load_graph("/home/flo/workspaces/rust/graphdata/bw.ch");
+
+    let start = Instant::now();
+    let labels = ae2::HubLabels::new(&graph);
+    let compressed = labels.compress();
+    let end = Instant::now();
+    println!(
+        "labels took {:?}, {:.1} hubs per label, {} MB, {} MB compressed",
+        end.duration_since(start),
+        labels.average_label_size(),
+        labels.heap_size_of_children() / 1048576,
+        compressed.heap_size_of_children() / 1048576
+    );
+    compressed.save("bw.hl").expect("Could not write labels");
+    let compressed = ae2::CompressedHubLabels::load("bw.hl").expect("Could not read labels");
+    let labels = compressed.decompress();
+
+    let tries = 10_000;
+    let mut rng = rand::thread_rng();
+    let queries: Vec<(NodeId, NodeId)> = (0..tries)
+        .map(|_| {
+            let source: NodeId = rng.gen();
+            let dest: NodeId = rng.gen();
+            (source % graph.node_count(), dest % graph.node_count())
+        })
+        .collect();
+    let start = Instant::now();
+    for &(s, t) in &queries {
+        labels.distance(s, t);
+    }
+    let plain = Instant::now();
+    for &(s, t) in &queries {
+        compressed.distance(s, t);
+    }
+    let end = Instant::now();
+    println!(
+        "{} queries took {:?} on plain and {:?} on compressed labels",
+        tries,
+        plain.duration_since(start) / tries,
+        end.duration_since(plain) / tries
+    );
+
+    let mut dijkstra = graph.dijkstra();
+    for &(s, t) in queries.iter().take(100) {
+        assert_eq!(labels.distance(s, t), dijkstra.distance(s, t));
+    }
+}
+
+#[allow(dead_code)]
+fn ae2_traffic_main() {
+    let mut graph1 = ae1::load_graph("/home/flo/workspaces/rust/graphdata/saarland.graph");
+    let mut graph2 = ae2::load_graph("/home/flo/workspaces/rust/graphdata/saarland.ch");
+
+    let tries = 1000;
+    let mut rng = rand::thread_rng();
+    let mut updates1 = Vec::with_capacity(tries);
+    let mut updates2 = Vec::with_capacity(tries);
+    while updates2.len() < tries {
+        let id: usize = rng.gen();
+        let id = id % graph2.edge_count();
+        if !graph2.is_shortcut(id) {
+            let (source, dest, length) = graph2.edge(id);
+            updates1.push((source, dest, length * 2));
+            updates2.push((id, length * 2));
+        }
+    }
+
+    let start = Instant::now();
+    graph1.update_weights(&updates1);
+    graph2.update_weights(&updates2);
+    let end = Instant::now();
+    println!("updating {} edges took {:?}", tries, end.duration_since(start));
+}
+
+#[allow(dead_code)]
+fn ae2_cch_main() {
+    let graph = ae1::load_graph("/home/flo/workspaces/rust/graphdata/saarland.graph");
+
+    let start = Instant::now();
+    let mut cch = ae2::Cch::new(&graph);
+    let end = Instant::now();
+    println!(
+        "CCH preprocessing took {:?}, {} edges",
+        end.duration_since(start),
+        cch.edge_count()
+    );
+
+    // travel time in tenths of a second
+    let mut travel_times = Vec::new();
+    for node in 0..graph.node_count() {
+        let speeds = graph.outgoing_speeds_for(node);
+        for (edge, &speed) in graph.outgoing_edges_for(node).iter().zip(speeds) {
+            travel_times.push(edge.weight() * 36 / speed.max(1));
+        }
+    }
+
+    for &(name, ref weights) in &[("distance", graph.edge_weights()), ("travel time", travel_times)] {
+        let start = Instant::now();
+        cch.customize(weights);
+        let end = Instant::now();
+        println!("customizing {} took {:?}", name, end.duration_since(start));
+    }
+
+    let tries = 1000;
+    let mut rng = rand::thread_rng();
+    let mut query = cch.query();
+    let start = Instant::now();
+    for _ in 0..tries {
+        let source: NodeId = rng.gen();
+        let dest: NodeId = rng.gen();
+        query.distance(source % graph.node_count(), dest % graph.node_count());
+    }
+    let end = Instant::now();
+    println!("{} CCH queries took {:?}", tries, end.duration_since(start));
+}
+
 #[allow(dead_code)]
 fn ae1_eq_ae2() {
     use std::usize;
@@ -127,13 +528,26 @@ fn ae4_main() {
 
     let mut args = env::args();
     let path = args.nth(1).expect("expect file argument");
-    let movies = ae4::load_movies(path).expect("movies could not be loaded");
-    let index = ae4::build_inverted_index(&movies);
+    // a saved index needs no movies, but phrase queries then rely on its
+    // positions and the naive search is not available
+    let (movies, index) = if path.ends_with(".idx") {
+        let start = Instant::now();
+        let index = ae4::InvertedIndex::load(&path).expect("index could not be loaded");
+        println!("loading index took: {:?}", start.elapsed());
+        (Vec::new(), index)
+    } else {
+        let movies = ae4::load_movies(path).expect("movies could not be loaded");
+        let index = ae4::build_inverted_index_with(&movies, true, ae4::Analyzer::english());
+        (movies, index)
+    };
     let naive = match args.next() {
-        Some(_) => true,
+        Some(_) => !movies.is_empty(),
         None => false,
     };
     println!("Inverted index is build");
+    for &field in &ae4::Field::ALL {
+        println!("{}: {} terms", field.name(), index.field(field).vocabulary.len());
+    }
     if naive {
         println!("naive search is active too");
     }
@@ -147,20 +561,209 @@ fn ae4_main() {
             "reading from stdin failed",
         );
         println!("looking for key {}", buf);
-        let ind_dur = ae4::query_index(&index, &movies, &buf);
+        match ae4::query_index(&index, &movies, &buf, None) {
+            Ok(result) => {
+                print_query_result(&index, &result);
+                println!("query duration: {:?}", result.duration);
+            }
+            Err(error) => println!("invalid query: {}", error),
+        }
+        let start = Instant::now();
+        let ranked = ae4::ranked_query(&index, &buf, 10, &ae4::Bm25::default());
+        let rank_dur = start.elapsed();
+        for (id, score) in ranked {
+            println!("{:.3}: {}", score, index.documents[id].title);
+            if let Some(movie) = movies.get(id) {
+                println!("    {}", movie.text);
+            }
+        }
+        println!("ranking duration: {:?}", rank_dur);
 
         if naive {
-            let naiv_dur = ae4::naive_query(&movies, &buf);
-            println!("vec duration: {:?}", naiv_dur);
+            let result = ae4::naive_query(&movies, &buf, &index.analyzer);
+            print_query_result(&index, &result);
+            println!("vec duration: {:?}", result.duration);
 
         }
 
-        println!("query duration: {:?}", ind_dur);
 
+    }
+
+
+}
 
+#[allow(dead_code)]
+fn print_query_result(index: &ae4::InvertedIndex, result: &ae4::QueryResult) {
+    match result.scores {
+        Some(ref scores) => {
+            for (&id, score) in result.ids.iter().zip(scores) {
+                println!("{:.3} {}: {}", score, id, index.documents[id].title);
+            }
+        }
+        None => {
+            for &id in &result.ids {
+                println!("{}: {}", id, index.documents[id].title);
+            }
+        }
     }
+    if !result.unknown_terms.is_empty() {
+        println!("unknown terms: {}", result.unknown_terms.join(", "));
+    }
+    for (term, similar) in &result.corrections {
+        println!("searched {} instead of {}", similar.join(" or "), term);
+    }
+    println!("{} results", result.ids.len());
+    println!();
+}
 
+/// Builds the positional index of a movie file and saves it for `ae4_main`.
+#[allow(dead_code)]
+fn ae4_save_index_main() {
+    use std::env;
 
+    let mut args = env::args();
+    let path = args.nth(1).expect("expect movie file argument");
+    let out = args.next().expect("expect index file argument");
+    let movies = ae4::load_movies(path).expect("movies could not be loaded");
+    let index = ae4::build_inverted_index(&movies, true);
+    println!("Index takes {} MB", index.heap_size_of_children() / 1048576);
+    let start = Instant::now();
+    index.save(&out).expect("index could not be saved");
+    println!("saving index took: {:?}", start.elapsed());
+}
+
+/// Compares the memory and conjunctive query times of the plain and the
+/// compressed posting lists.
+#[allow(dead_code)]
+fn ae4_compressed_main() {
+    use std::env;
+    use std::io::{BufRead, stdin};
+
+    let path = env::args().nth(1).expect("expect file argument");
+    let movies = ae4::load_movies(path).expect("movies could not be loaded");
+    let index = ae4::build_inverted_index(&movies, false);
+    let descriptions: &ae4::FieldIndex = index.field(ae4::Field::Description);
+    let start = Instant::now();
+    let decoded: Vec<Vec<ae4::Posting>> = descriptions.postings.iter().map(|l| l.iter().collect()).collect();
+    println!("decoding took: {:?}", start.elapsed());
+    println!(
+        "postings take {} KB compressed, {} KB decoded",
+        descriptions.postings.heap_size_of_children() / 1024,
+        decoded.heap_size_of_children() / 1024
+    );
+
+    let stdin = stdin();
+    for line in stdin.lock().lines() {
+        let line = line.expect("reading from stdin failed");
+        let query = match ae4::parse_query(&line) {
+            Ok(query) => query,
+            Err(error) => {
+                println!("invalid query: {}", error);
+                continue;
+            }
+        };
+        let terms = query.terms(false);
+
+        let start = Instant::now();
+        let lists: Vec<Vec<usize>> = terms
+            .iter()
+            .map(|t| index.get(t).map_or_else(Vec::new, |list| list.cursor().collect()))
+            .collect();
+        let plain = if lists.is_empty() {
+            Vec::new()
+        } else {
+            lists[1..].iter().fold(lists[0].clone(), |r, l| ae4::intersect(&r, l))
+        };
+        let plain_dur = start.elapsed();
+        let start = Instant::now();
+        let galloping = if lists.is_empty() {
+            Vec::new()
+        } else {
+            lists[1..].iter().fold(lists[0].clone(), |r, l| ae4::galloping_intersect(&r, l))
+        };
+        let galloping_dur = start.elapsed();
+        let start = Instant::now();
+        let ordered = ae4::intersect_all(&lists);
+        let ordered_dur = start.elapsed();
+        let start = Instant::now();
+        let result = descriptions.and_query(&terms);
+        let compressed_dur = start.elapsed();
+        assert_eq!(plain, result);
+        assert_eq!(galloping, result);
+        assert_eq!(ordered, result);
+        println!(
+            "{} results, linear merge {:?}, galloping {:?}, shortest first {:?}, compressed {:?}",
+            result.len(),
+            plain_dur,
+            galloping_dur,
+            ordered_dur,
+            compressed_dur
+        );
+    }
+}
+
+/// Indexes the first half of a movie file at once and the rest movie by
+/// movie, then reads commands: `+title<TAB>description` adds a movie, `-id`
+/// deletes one, `merge` merges all segments and anything else is a query.
+#[allow(dead_code)]
+fn ae4_segments_main() {
+    use std::env;
+    use std::io::{BufRead, stdin};
+
+    let path = env::args().nth(1).expect("expect file argument");
+    let movies = ae4::load_movies(path).expect("movies could not be loaded");
+    let mut index = ae4::SegmentedIndex::new(ae4::Analyzer::english());
+    let start = Instant::now();
+    let half = movies.len() / 2;
+    index.add_all(&movies[..half]);
+    println!("adding {} movies at once took: {:?}", half, start.elapsed());
+    let start = Instant::now();
+    for movie in &movies[half..] {
+        index.add(movie);
+    }
+    println!(
+        "adding {} movies one by one took: {:?}, {} segments",
+        movies.len() - half,
+        start.elapsed(),
+        index.segment_count()
+    );
+
+    let stdin = stdin();
+    for line in stdin.lock().lines() {
+        let line = line.expect("reading from stdin failed");
+        if let Some(movie) = line.strip_prefix('+') {
+            match movie.parse::<ae4::Movie>() {
+                Ok(movie) => println!("added as {}", index.add(&movie)),
+                Err(error) => println!("invalid movie: {}", error),
+            }
+        } else if let Some(id) = line.strip_prefix('-') {
+            match id.parse() {
+                Ok(id) if index.delete(id) => println!("deleted {}", id),
+                _ => println!("no movie {}", id),
+            }
+        } else if line == "merge" {
+            let start = Instant::now();
+            index.merge();
+            println!("merge took: {:?}, {} movies left", start.elapsed(), index.len());
+            if index.is_empty() {
+                println!("all movies were deleted");
+            }
+        } else {
+            match index.query(&line) {
+                Ok(result) => {
+                    println!("found {} in {:?}", result.ids.len(), result.duration);
+                    for &id in result.ids.iter().take(10) {
+                        println!("{}: {}", id, index.title(id).unwrap_or_default());
+                    }
+                    if !result.unknown_terms.is_empty() {
+                        println!("unknown terms: {}", result.unknown_terms.join(", "));
+                    }
+                }
+                Err(error) => println!("invalid query: {}", error),
+            }
+        }
+    }
+    println!("{} movies, index takes {} KB", index.len(), index.heap_size_of_children() / 1024);
 }
 
 fn ae5_main() {